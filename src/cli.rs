@@ -1,11 +1,24 @@
+use argo_hermes::k8s::routing::get_routes;
+use argo_hermes::k8s::services::get_service_definitions;
+use argo_hermes::k8s::state::K8sStateStore;
 use argo_hermes::k8s::templates::K8sTemplateRegistry;
+use argo_hermes::k8s::workflows::K8sWorkflowController;
+use argo_hermes::routing::{parse_routes, Routes};
+use argo_hermes::secrets::Secret;
 use argo_hermes::server::filters;
 use argo_hermes::services::registries::DefaultServiceRegistry;
+use argo_hermes::services::{parse_definitions, setup_all, ServiceDefinitions, ServiceRegistryRef};
+use argo_hermes::state::stores::MemoryStateStore;
+use argo_hermes::state::StateStoreRef;
 use clap::{App, Arg};
+use std::collections::HashSet;
+use std::fs;
 use std::io;
 use std::net::SocketAddr;
 use std::net::ToSocketAddrs;
 use std::process;
+use std::sync::Arc;
+use warp::{Filter, Reply};
 
 const PKG_NAME: &str = env!("CARGO_PKG_NAME");
 const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -29,6 +42,70 @@ fn main() -> io::Result<()> {
                 .takes_value(true)
                 .help("Port to bind Hermes to [default: 3030]"),
         )
+        .arg(
+            Arg::new("slack-signing-secret")
+                .long("slack-signing-secret")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Path to a file with the Slack signing secret. Enables the interactivity endpoint"),
+        )
+        .arg(
+            Arg::new("slack-approvers")
+                .long("slack-approvers")
+                .takes_value(true)
+                .value_name("USER_IDS")
+                .use_delimiter(true)
+                .requires("slack-signing-secret")
+                .help("Comma-separated IDs of the Slack users allowed to resume or stop the workflows [default: anyone]"),
+        )
+        .arg(
+            Arg::new("state-store")
+                .long("state-store")
+                .takes_value(true)
+                .possible_values(["memory", "configmap"])
+                .help("Where to persist the service instances and message state [default: memory]"),
+        )
+        .arg(
+            Arg::new("services")
+                .long("services")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Path to a YAML file with service instances to set up at startup"),
+        )
+        .arg(
+            Arg::new("plugin-token")
+                .long("plugin-token")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Path to a file with the token the plugin requests have to be authenticated with, e.g. /var/run/argo/token"),
+        )
+        .arg(
+            Arg::new("argo-ui-url")
+                .long("argo-ui-url")
+                .takes_value(true)
+                .value_name("URL")
+                .help("Base URL of the Argo UI, used to link to the workflows from the notifications"),
+        )
+        .arg(
+            Arg::new("services-from-configmaps")
+                .long("services-from-configmaps")
+                .help("Set up the service instances defined in the labelled ConfigMaps at startup"),
+        )
+        .arg(
+            Arg::new("routes")
+                .long("routes")
+                .takes_value(true)
+                .value_name("FILE")
+                .conflicts_with("routes-configmap")
+                .help("Path to a YAML file with rules routing the notifications without a target"),
+        )
+        .arg(
+            Arg::new("routes-configmap")
+                .long("routes-configmap")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("Name of a ConfigMap with rules routing the notifications without a target"),
+        )
         .get_matches();
     let port: u16 = matches
         .value_of("port")
@@ -55,18 +132,150 @@ fn main() -> io::Result<()> {
                 process::exit(1);
             })
     };
-    serve(addr);
+    let slack_interactivity = matches.value_of("slack-signing-secret").map(|path| {
+        let signing_secret = fs::read_to_string(path)
+            .map(|s| Secret::new(s.trim().to_string()))
+            .unwrap_or_else(|err| {
+                println!("Could not read the Slack signing secret: {}", err);
+                process::exit(1);
+            });
+        SlackInteractivity {
+            signing_secret,
+            approvers: matches
+                .values_of("slack-approvers")
+                .map(|ids| ids.map(|id| id.trim().to_string()).collect()),
+        }
+    });
+    let state_store = matches
+        .value_of("state-store")
+        .unwrap_or("memory")
+        .to_string();
+    let plugin_token = matches.value_of("plugin-token").map(|path| {
+        fs::read_to_string(path)
+            .map(|s| Secret::new(s.trim().to_string()))
+            .unwrap_or_else(|err| {
+                println!("Could not read the plugin token: {}", err);
+                process::exit(1);
+            })
+    });
+    let services = matches.value_of("services").map(|path| {
+        fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|s| parse_definitions(&s))
+            .unwrap_or_else(|err| {
+                println!("Could not read the service definitions: {}", err);
+                process::exit(1);
+            })
+    });
+    let routes = matches.value_of("routes").map(|path| {
+        fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|s| parse_routes(&s))
+            .unwrap_or_else(|err| {
+                println!("Could not read the routes: {}", err);
+                process::exit(1);
+            })
+    });
+    serve(
+        addr,
+        slack_interactivity,
+        state_store,
+        services.unwrap_or_default(),
+        matches.is_present("services-from-configmaps"),
+        matches.value_of("routes-configmap").map(String::from),
+        filters::Settings {
+            argo_ui_url: matches.value_of("argo-ui-url").map(String::from),
+            plugin_token,
+            routes: Arc::new(routes.unwrap_or_default()),
+            windows: Default::default(),
+            throttles: Default::default(),
+        },
+    );
     Ok(())
 }
 
+/// Settings of the Slack interactivity endpoint
+struct SlackInteractivity {
+    /// The signing secret of the Slack app
+    signing_secret: Secret,
+    /// IDs of the Slack users allowed to resume or stop the workflows, anyone when not set
+    approvers: Option<HashSet<String>>,
+}
+
 #[tokio::main]
-async fn serve(addr: SocketAddr) {
-    let service_registry = DefaultServiceRegistry::with_default_services();
+async fn serve(
+    addr: SocketAddr,
+    slack_interactivity: Option<SlackInteractivity>,
+    state_store: String,
+    mut services: ServiceDefinitions,
+    services_from_configmaps: bool,
+    routes_configmap: Option<String>,
+    mut settings: filters::Settings,
+) {
+    let service_registry: ServiceRegistryRef = DefaultServiceRegistry::with_default_services();
+    if services_from_configmaps {
+        services.extend(get_service_definitions().await.unwrap_or_else(|err| {
+            println!("Could not retrieve the service definitions: {}", err);
+            process::exit(1);
+        }));
+    }
+    settings.windows = Arc::new(
+        services
+            .iter()
+            .filter_map(|(alias, d)| d.window.clone().map(|w| (alias.clone(), w)))
+            .collect(),
+    );
+    settings.throttles = Arc::new(
+        services
+            .iter()
+            .filter_map(|(alias, d)| d.throttle.map(|t| (alias.clone(), t)))
+            .collect(),
+    );
+    setup_all(&service_registry, services)
+        .await
+        .unwrap_or_else(|err| {
+            println!("{}", err);
+            process::exit(1);
+        });
+    if let Some(name) = routes_configmap {
+        let routes: Routes = get_routes(&name).await.unwrap_or_else(|err| {
+            println!("Could not retrieve the routes: {}", err);
+            process::exit(1);
+        });
+        settings.routes = Arc::new(routes);
+    }
     let template_registry = K8sTemplateRegistry::new()
         .await
         .expect("Failed to init k8s template registry");
+    let state_store: StateStoreRef = match state_store.as_str() {
+        "configmap" => K8sStateStore::new()
+            .await
+            .expect("Failed to init k8s state store"),
+        _ => MemoryStateStore::new(),
+    };
+
+    let api = filters::routes(service_registry, template_registry, state_store, settings);
+    match slack_interactivity {
+        Some(interactivity) => {
+            let workflow_controller = K8sWorkflowController::new()
+                .await
+                .expect("Failed to init k8s workflow controller");
+            let interactions = filters::interactions(
+                interactivity.signing_secret,
+                interactivity.approvers,
+                workflow_controller,
+            );
+            run(api.or(interactions), addr).await
+        }
+        None => run(api, addr).await,
+    }
+}
 
-    let api = filters::routes(service_registry, template_registry);
+async fn run<F>(api: F, addr: SocketAddr)
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+{
     let (addr, server) = warp::serve(api)
         .try_bind_with_graceful_shutdown(addr, async {
             tokio::signal::ctrl_c()
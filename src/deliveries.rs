@@ -0,0 +1,193 @@
+use crate::services::Delivery;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long the outcome of a delivery is kept around after it has been reported to Argo
+///
+/// Argo stops asking about a node as soon as it learns its final outcome, but the outcome is kept
+/// for a bit longer than Argo waits between its requests anyway, so that a lost response does not
+/// result in the notification being sent twice. It is not kept any longer, as a retry of the node,
+/// or another node with the same template, is identified by the same key and has to be delivered
+/// again.
+const GRACE: Duration = Duration::from_secs(15);
+
+/// How long the outcome of a delivery is kept around when Argo does not ask about it, i.e. because
+/// its workflow has been deleted in the meantime
+const RETENTION: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone)]
+pub enum DeliveryStatus {
+    /// The notification is still being delivered
+    Pending,
+    /// The delivery has finished, either successfully or not
+    Done(Result<Delivery, String>),
+}
+
+/// A delivery tracked under a key
+struct Tracked {
+    status: DeliveryStatus,
+    /// When the delivery has been registered, completed or reported, whichever happened last
+    updated_at: Instant,
+    /// Whether the outcome of the delivery has been reported to Argo
+    reported: bool,
+}
+
+impl Tracked {
+    /// Returns the status of the delivery, which counts as reported once it is done
+    fn report(&mut self) -> DeliveryStatus {
+        if matches!(self.status, DeliveryStatus::Done(_)) && !self.reported {
+            self.reported = true;
+            self.updated_at = Instant::now();
+        }
+        self.status.clone()
+    }
+}
+
+/// The notifications batched together, until their digest is delivered
+struct Batch {
+    contexts: Vec<serde_json::Value>,
+    opened_at: Instant,
+    window: Duration,
+}
+
+/// Tracks the notifications delivered in the background
+///
+/// Every delivery is identified by a key, derived from the node that requested it, so that the
+/// subsequent calls for the same node can be answered with the outcome of the delivery. The outcome
+/// is kept until it has been reported to Argo, and briefly after that.
+///
+/// It also buffers the contexts of the batched notifications until their digest is delivered.
+#[derive(Default)]
+pub struct DeliveryQueue {
+    deliveries: Mutex<HashMap<String, Tracked>>,
+    batches: Mutex<HashMap<String, Batch>>,
+}
+
+pub type DeliveryQueueRef = Arc<DeliveryQueue>;
+
+impl DeliveryQueue {
+    pub fn new() -> DeliveryQueueRef {
+        Arc::new(Self::default())
+    }
+
+    /// Returns the status of the delivery tracked under the given key, if any
+    ///
+    /// The caller is expected to report the returned outcome of a finished delivery to Argo.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key identifying the delivery
+    pub fn status(&self, key: &str) -> Option<DeliveryStatus> {
+        let mut deliveries = self.deliveries.lock();
+        prune(&mut deliveries);
+        deliveries.get_mut(key).map(Tracked::report)
+    }
+
+    /// Registers a new delivery, unless one is already tracked under the given key
+    ///
+    /// Returns the status of the already tracked delivery, if any, the same way as `status`.
+    /// Otherwise the caller is responsible for carrying out the delivery and recording its outcome
+    /// using `complete`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key identifying the delivery
+    pub fn enqueue(&self, key: &str) -> Option<DeliveryStatus> {
+        let mut deliveries = self.deliveries.lock();
+        prune(&mut deliveries);
+        if let Some(tracked) = deliveries.get_mut(key) {
+            return Some(tracked.report());
+        }
+        deliveries.insert(
+            key.into(),
+            Tracked {
+                status: DeliveryStatus::Pending,
+                updated_at: Instant::now(),
+                reported: false,
+            },
+        );
+        None
+    }
+
+    /// Records the outcome of a delivery
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key identifying the delivery
+    /// * `result` - The outcome of the delivery
+    pub fn complete(&self, key: &str, result: Result<Delivery, String>) {
+        self.record(key, result, false);
+    }
+
+    /// Records the outcome of a delivery that is reported to Argo right away
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key identifying the delivery
+    /// * `result` - The outcome of the delivery
+    pub fn report(&self, key: &str, result: Result<Delivery, String>) {
+        self.record(key, result, true);
+    }
+
+    fn record(&self, key: &str, result: Result<Delivery, String>, reported: bool) {
+        self.deliveries.lock().insert(
+            key.into(),
+            Tracked {
+                status: DeliveryStatus::Done(result),
+                updated_at: Instant::now(),
+                reported,
+            },
+        );
+    }
+
+    /// Adds the context of a notification to a batch, opening the batch if there is none yet
+    ///
+    /// Returns the number of the notifications in the batch, i.e. 1 when the batch has just been
+    /// opened.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key identifying the batch
+    /// * `window` - How long the batch stays open for
+    /// * `context` - The context of the notification
+    pub fn add_to_batch(&self, key: &str, window: Duration, context: serde_json::Value) -> usize {
+        let mut batches = self.batches.lock();
+        // A batch nobody has closed in time is abandoned rather than kept forever
+        batches.retain(|_, b| b.opened_at.elapsed() < b.window + GRACE);
+        let batch = batches.entry(key.into()).or_insert_with(|| Batch {
+            contexts: vec![],
+            opened_at: Instant::now(),
+            window,
+        });
+        batch.contexts.push(context);
+        batch.contexts.len()
+    }
+
+    /// Closes a batch, returning the contexts of its notifications
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key identifying the batch
+    pub fn take_batch(&self, key: &str) -> Vec<serde_json::Value> {
+        self.batches
+            .lock()
+            .remove(key)
+            .map(|b| b.contexts)
+            .unwrap_or_default()
+    }
+}
+
+/// Drops the outcomes reported more than `GRACE` ago, and the ones never reported that have
+/// been completed more than `RETENTION` ago
+///
+/// The pending deliveries are kept regardless of their age, as they might be waiting for their
+/// delivery window to open.
+fn prune(deliveries: &mut HashMap<String, Tracked>) {
+    deliveries.retain(|_, tracked| match tracked.status {
+        DeliveryStatus::Pending => true,
+        DeliveryStatus::Done(_) if tracked.reported => tracked.updated_at.elapsed() < GRACE,
+        DeliveryStatus::Done(_) => tracked.updated_at.elapsed() < RETENTION,
+    });
+}
@@ -78,6 +78,273 @@ pub mod templates {
     }
 }
 
+pub mod workflows {
+    use super::*;
+    use crate::workflows::{WorkflowController, WorkflowError};
+    use async_trait::async_trait;
+    use k8s_openapi::chrono::{SecondsFormat, Utc};
+    use kube::api::{ApiResource, DynamicObject, GroupVersionKind, Patch, PatchParams};
+    use std::sync::Arc;
+
+    /// Controls Argo workflows by patching the Workflow resources using the Kubernetes API
+    pub struct K8sWorkflowController {
+        client: Client,
+        resource: ApiResource,
+    }
+
+    impl K8sWorkflowController {
+        pub async fn new() -> Result<Arc<Self>, String> {
+            Ok(Arc::from(Self {
+                client: Client::try_default()
+                    .await
+                    .map_err(|e| format!("Kubernetes client error: {:#?}", e))?,
+                resource: ApiResource::from_gvk(&GroupVersionKind::gvk(
+                    "argoproj.io",
+                    "v1alpha1",
+                    "Workflow",
+                )),
+            }))
+        }
+
+        fn api(&self) -> Api<DynamicObject> {
+            Api::default_namespaced_with(self.client.clone(), &self.resource)
+        }
+
+        async fn patch(&self, name: &str, patch: serde_json::Value) -> Result<(), WorkflowError> {
+            self.api()
+                .patch(name, &PatchParams::default(), &Patch::Merge(&patch))
+                .await
+                .map(|_| ())
+                .map_err(|e| {
+                    WorkflowError::GenericError(format!("Failed to patch Workflow: {}", e))
+                })
+        }
+    }
+
+    #[async_trait]
+    impl WorkflowController for K8sWorkflowController {
+        /// Resumes a workflow the same way `argo resume` does, by marking the running suspend
+        /// nodes as succeeded
+        async fn resume(&self, name: &str) -> Result<(), WorkflowError> {
+            let workflow = self.api().get(name).await.map_err(|e| match e {
+                kube::Error::Api(e) if e.code == 404 => WorkflowError::NotFound,
+                e => WorkflowError::GenericError(format!("Failed to retrieve Workflow: {}", e)),
+            })?;
+            let finished_at = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+            let nodes: serde_json::Map<_, _> = workflow.data["status"]["nodes"]
+                .as_object()
+                .into_iter()
+                .flatten()
+                .filter(|(_, node)| node["type"] == "Suspend" && node["phase"] == "Running")
+                .map(|(id, _)| {
+                    let node = serde_json::json!({
+                        "phase": "Succeeded",
+                        "finishedAt": finished_at,
+                    });
+                    (id.clone(), node)
+                })
+                .collect();
+            if nodes.is_empty() && workflow.data["spec"]["suspend"] != true {
+                return Err(WorkflowError::InvalidState(
+                    "Workflow is not suspended".into(),
+                ));
+            }
+            self.patch(
+                name,
+                serde_json::json!({
+                    "spec": {"suspend": null},
+                    "status": {"nodes": nodes},
+                }),
+            )
+            .await
+        }
+
+        async fn stop(&self, name: &str) -> Result<(), WorkflowError> {
+            self.patch(name, serde_json::json!({"spec": {"shutdown": "Stop"}}))
+                .await
+        }
+    }
+}
+
+pub mod state {
+    use super::*;
+    use crate::state::{Scope, ServiceState, StateError, StateStore};
+    use async_trait::async_trait;
+    use k8s_openapi::api::core::v1::ConfigMap;
+    use kube::api::{Patch, PatchParams, PostParams};
+    use std::sync::Arc;
+
+    /// A state store backed by Kubernetes ConfigMaps
+    ///
+    /// The state of every workflow is kept in a separate ConfigMap, with a key per service
+    /// instance. The ConfigMaps are owned by their workflows, so that they are garbage collected
+    /// together with them.
+    pub struct K8sStateStore {
+        client: Client,
+    }
+
+    impl K8sStateStore {
+        pub async fn new() -> Result<Arc<Self>, String> {
+            Ok(Arc::from(Self {
+                client: Client::try_default()
+                    .await
+                    .map_err(|e| format!("Kubernetes client error: {:#?}", e))?,
+            }))
+        }
+
+        fn name(scope: &Scope) -> String {
+            format!("hermes-state-{}", scope.uid)
+        }
+
+        /// Builds the ConfigMap holding the state of a workflow, owned by the workflow
+        fn config_map(scope: &Scope, data: serde_json::Value) -> Result<ConfigMap, StateError> {
+            let owner_references = scope.name.as_ref().map(|name| {
+                serde_json::json!([{
+                    "apiVersion": "argoproj.io/v1alpha1",
+                    "kind": "Workflow",
+                    "name": name,
+                    "uid": scope.uid,
+                }])
+            });
+            serde_json::from_value(serde_json::json!({
+                "metadata": {
+                    "name": Self::name(scope),
+                    "labels": {"app.kubernetes.io/managed-by": "hermes"},
+                    "ownerReferences": owner_references,
+                },
+                "data": data,
+            }))
+            .map_err(|e| StateError::GenericError(format!("Invalid ConfigMap: {}", e)))
+        }
+    }
+
+    #[async_trait]
+    impl StateStore for K8sStateStore {
+        async fn get(
+            &self,
+            scope: &Scope,
+            alias: &str,
+        ) -> Result<Option<ServiceState>, StateError> {
+            let api: Api<ConfigMap> = Api::default_namespaced(self.client.clone());
+            let config_map = match api.get(&Self::name(scope)).await {
+                Ok(config_map) => config_map,
+                Err(kube::Error::Api(e)) if e.code == 404 => return Ok(None),
+                Err(e) => {
+                    return Err(StateError::GenericError(format!(
+                        "Failed to retrieve ConfigMap: {}",
+                        e
+                    )))
+                }
+            };
+            config_map
+                .data
+                .and_then(|mut data| data.remove(alias))
+                .map(|state| serde_json::from_str(&state))
+                .transpose()
+                .map_err(|e| StateError::GenericError(format!("Invalid state: {}", e)))
+        }
+
+        async fn put(
+            &self,
+            scope: &Scope,
+            alias: &str,
+            state: ServiceState,
+        ) -> Result<(), StateError> {
+            let api: Api<ConfigMap> = Api::default_namespaced(self.client.clone());
+            let state = serde_json::to_string(&state)
+                .map_err(|e| StateError::GenericError(format!("Invalid state: {}", e)))?;
+            let data = serde_json::json!({ alias: state });
+            let patch = serde_json::json!({ "data": data });
+            let patch = Patch::Merge(&patch);
+            let (name, params) = (Self::name(scope), PatchParams::default());
+            let update = || api.patch(&name, &params, &patch);
+            let update_failed =
+                |e| StateError::GenericError(format!("Failed to update ConfigMap: {}", e));
+            match update().await {
+                Ok(_) => return Ok(()),
+                Err(kube::Error::Api(e)) if e.code == 404 => {}
+                Err(e) => return Err(update_failed(e)),
+            }
+            let config_map = Self::config_map(scope, data)?;
+            match api.create(&PostParams::default(), &config_map).await {
+                Ok(_) => Ok(()),
+                // Another write has created the ConfigMap in the meantime, so merge into it
+                Err(kube::Error::Api(e)) if e.code == 409 => {
+                    update().await.map(|_| ()).map_err(update_failed)
+                }
+                Err(e) => Err(StateError::GenericError(format!(
+                    "Failed to create ConfigMap: {}",
+                    e
+                ))),
+            }
+        }
+    }
+}
+
+pub mod services {
+    use super::*;
+    use crate::services::{ServiceDefinition, ServiceDefinitions};
+    use k8s_openapi::api::core::v1::ConfigMap;
+    use kube::api::ListParams;
+
+    /// The label marking the ConfigMaps holding service definitions
+    pub const SERVICES_LABEL: &str = "hermes/configmap-type=services";
+
+    /// Retrieves the service definitions from all the labelled ConfigMaps in the namespace
+    ///
+    /// Every key of a ConfigMap is an alias, with a YAML service definition as its value.
+    pub async fn get_service_definitions() -> Result<ServiceDefinitions, String> {
+        let client = Client::try_default()
+            .await
+            .map_err(|e| format!("Kubernetes client error: {:#?}", e))?;
+        let api: Api<ConfigMap> = Api::default_namespaced(client);
+        let config_maps = api
+            .list(&ListParams::default().labels(SERVICES_LABEL))
+            .await
+            .map_err(|e| format!("Failed to list ConfigMaps: {}", e))?;
+        let mut definitions = ServiceDefinitions::new();
+        for config_map in config_maps {
+            let name = config_map.metadata.name.unwrap_or_default();
+            for (alias, source) in config_map.data.unwrap_or_default() {
+                let definition: ServiceDefinition = serde_yaml::from_str(&source)
+                    .map_err(|e| format!("ConfigMap \"{}\", service \"{}\": {}", name, alias, e))?;
+                definitions.insert(alias, definition);
+            }
+        }
+        Ok(definitions)
+    }
+}
+
+pub mod routing {
+    use super::*;
+    use crate::routing::{parse_routes, Routes};
+    use k8s_openapi::api::core::v1::ConfigMap;
+
+    /// The key of the ConfigMap holding the routing rules
+    pub const ROUTES_KEY: &str = "routes";
+
+    /// Retrieves the routing rules from a ConfigMap
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the ConfigMap, holding a YAML list of rules under the `routes` key
+    pub async fn get_routes(name: &str) -> Result<Routes, String> {
+        let client = Client::try_default()
+            .await
+            .map_err(|e| format!("Kubernetes client error: {:#?}", e))?;
+        let api: Api<ConfigMap> = Api::default_namespaced(client);
+        let config_map = api
+            .get(name)
+            .await
+            .map_err(|e| format!("Failed to retrieve ConfigMap: {}", e))?;
+        let source = config_map
+            .data
+            .and_then(|mut data| data.remove(ROUTES_KEY))
+            .ok_or_else(|| format!("ConfigMap \"{}\" is missing the `{}` key", name, ROUTES_KEY))?;
+        parse_routes(&source)
+    }
+}
+
 pub mod secrets {
     use super::*;
     use k8s_openapi::api::core::v1::Secret;
@@ -96,6 +363,14 @@ pub mod secrets {
         res
     }
 
+    /// Retrieves a Secret and maps its data onto the given type
+    ///
+    /// The sensitive fields of the type should be declared as `crate::secrets::Secret`, so that
+    /// they never show up in error messages.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the Secret to retrieve
     pub async fn get_secret<T>(name: &str) -> Result<T, String>
     where
         T: serde::de::DeserializeOwned,
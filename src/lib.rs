@@ -1,4 +1,12 @@
+pub mod deliveries;
 pub mod k8s;
+pub mod period;
+pub mod routing;
+pub mod secrets;
 pub mod server;
 pub mod services;
+pub mod state;
 pub mod templates;
+pub mod throttling;
+pub mod windows;
+pub mod workflows;
@@ -0,0 +1,84 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// A rule routing the matching notifications to a service instance
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Route {
+    /// Values the context has to hold for the rule to match, keyed by their dotted paths, e.g.
+    /// `hermes.workflow.labels.team`. A rule without any matchers matches every notification
+    #[serde(default, rename = "match")]
+    pub matchers: BTreeMap<String, String>,
+    /// The alias of the service instance to deliver the notification to
+    pub target: String,
+    /// Service specific config, replacing the one given by the notify step
+    #[serde(default)]
+    pub config: Option<serde_json::Value>,
+    /// The template to use, replacing the one given by the notify step
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Whether to keep matching the following rules after this one has matched
+    #[serde(default, rename = "continue")]
+    pub continue_matching: bool,
+}
+
+impl Route {
+    /// Checks whether the rule matches the given context
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - The context of the notification
+    pub fn matches(&self, context: &serde_json::Value) -> bool {
+        self.matchers.iter().all(|(path, expected)| {
+            let value = path
+                .split('.')
+                .try_fold(context, |value, key| value.get(key));
+            match value {
+                Some(serde_json::Value::String(s)) => s == expected,
+                Some(v @ (serde_json::Value::Number(_) | serde_json::Value::Bool(_))) => {
+                    serde_json::from_str::<serde_json::Value>(expected).is_ok_and(|e| e == *v)
+                }
+                _ => false,
+            }
+        })
+    }
+}
+
+/// Ordered rules deciding where the notifications without an explicit target are delivered to
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Routes(Vec<Route>);
+
+impl Routes {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Lists the rules matching the given context
+    ///
+    /// The rules are tried in order and the matching stops at the first matching rule, unless it
+    /// asks to continue.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - The context of the notification
+    pub fn route(&self, context: &serde_json::Value) -> Vec<&Route> {
+        let mut routes = vec![];
+        for route in self.0.iter().filter(|r| r.matches(context)) {
+            routes.push(route);
+            if !route.continue_matching {
+                break;
+            }
+        }
+        routes
+    }
+}
+
+/// Parses routing rules from a YAML (or JSON) list
+///
+/// # Arguments
+///
+/// * `source` - The document to parse
+pub fn parse_routes(source: &str) -> Result<Routes, String> {
+    serde_yaml::from_str(source).map_err(|e| format!("Invalid routes: {}", e))
+}
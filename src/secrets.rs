@@ -0,0 +1,72 @@
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashSet;
+use std::fmt;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Secrets shorter than this are not scrubbed, as they would mangle unrelated text
+const MIN_SCRUB_LENGTH: usize = 4;
+
+lazy_static! {
+    /// Holds the values of all the secrets known to the process
+    static ref KNOWN_SECRETS: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+/// A sensitive value, i.e. an authentication token
+///
+/// The value is redacted when formatted, so it does not end up in error messages or logs by
+/// accident. Additionally, every secret is remembered, so that it can be removed from any text
+/// using `scrub`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: String) -> Self {
+        if value.len() >= MIN_SCRUB_LENGTH {
+            KNOWN_SECRETS.lock().insert(value.clone());
+        }
+        Self(value)
+    }
+
+    /// Returns the actual value of the secret
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Secret({})", REDACTED)
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer).map(Secret::new)
+    }
+}
+
+/// Replaces the values of all the known secrets in the given text
+///
+/// # Arguments
+///
+/// * `text` - The text to scrub, i.e. an error message
+pub fn scrub(text: &str) -> String {
+    KNOWN_SECRETS
+        .lock()
+        .iter()
+        .fold(text.to_string(), |text, secret| {
+            text.replace(secret.as_str(), REDACTED)
+        })
+}
@@ -1,26 +1,122 @@
 pub mod filters {
     use super::handlers;
+    use crate::deliveries::DeliveryQueue;
+    use crate::routing::Routes;
+    use crate::secrets::Secret;
     use crate::services::ServiceRegistryRef;
+    use crate::state::StateStoreRef;
     use crate::templates::TemplateRegistryRef;
+    use crate::throttling::{Limiter, Throttles};
+    use crate::windows::DeliveryWindows;
+    use crate::workflows::WorkflowControllerRef;
+    use ring::constant_time;
+    use std::collections::HashSet;
+    use std::sync::Arc;
     use warp::Filter;
 
+    /// Settings of the plugin API that do not warrant a registry of their own
+    #[derive(Debug, Clone, Default)]
+    pub struct Settings {
+        /// Base URL of the Argo UI, used to link to the workflows from the notifications
+        pub argo_ui_url: Option<String>,
+        /// The token the plugin requests have to be authenticated with. Any request is accepted
+        /// when not set
+        pub plugin_token: Option<Secret>,
+        /// Rules routing the notifications that do not specify any target
+        pub routes: Arc<Routes>,
+        /// The delivery windows of the service instances
+        pub windows: Arc<DeliveryWindows>,
+        /// The throttles of the service instances
+        pub throttles: Arc<Throttles>,
+    }
+
     pub fn routes(
         service_registry: ServiceRegistryRef,
         template_registry: TemplateRegistryRef,
+        state_store: StateStoreRef,
+        settings: Settings,
     ) -> impl warp::Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-        template_execute(service_registry, template_registry)
+        template_execute(service_registry, template_registry, state_store, settings)
     }
 
     fn template_execute(
         service_registry: ServiceRegistryRef,
         template_registry: TemplateRegistryRef,
+        state_store: StateStoreRef,
+        settings: Settings,
     ) -> impl warp::Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        let queue = DeliveryQueue::new();
+        let limiter = Limiter::with_throttles(settings.throttles.clone());
         warp::path!("api" / "v1" / "template.execute")
             .and(warp::post())
-            .and(warp::body::json())
+            .and(authorization(settings.plugin_token.clone()))
+            .and(warp::body::bytes())
             .and(with_service_registry(service_registry))
             .and(with_template_registry(template_registry))
+            .and(with_state_store(state_store))
+            .and(warp::any().map(move || settings.clone()))
+            .and(warp::any().map(move || queue.clone()))
+            .and(warp::any().map(move || limiter.clone()))
             .and_then(handlers::dispatch)
+            .recover(handlers::forbidden)
+    }
+
+    /// Requires the requests to carry the given bearer token, as sent by the Argo agent
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The expected token. Any request is accepted when not set
+    fn authorization(
+        token: Option<Secret>,
+    ) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+        warp::header::optional::<String>("authorization")
+            .and_then(move |header: Option<String>| {
+                let token = token.clone();
+                async move {
+                    let token = match token {
+                        Some(token) => token,
+                        None => return Ok(()),
+                    };
+                    let given = header
+                        .as_deref()
+                        .and_then(|h| h.strip_prefix("Bearer "))
+                        .unwrap_or_default();
+                    constant_time::verify_slices_are_equal(
+                        given.as_bytes(),
+                        token.expose().as_bytes(),
+                    )
+                    .map_err(|_| warp::reject::custom(handlers::Forbidden))
+                }
+            })
+            .untuple_one()
+    }
+
+    /// Handles the Slack interactivity requests, i.e. button clicks
+    ///
+    /// # Arguments
+    ///
+    /// * `signing_secret` - The signing secret of the Slack app, used to verify the requests
+    /// * `approvers` - IDs of the Slack users allowed to resume or stop the workflows. Anyone
+    ///   able to click the buttons is allowed when not set
+    /// * `workflow_controller` - Used to resume or stop the workflows
+    pub fn interactions(
+        signing_secret: Secret,
+        approvers: Option<HashSet<String>>,
+        workflow_controller: WorkflowControllerRef,
+    ) -> impl warp::Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        let approvers = approvers.map(Arc::new);
+        // The client pools its connections, so it is shared by all the requests
+        let client = reqwest::Client::new();
+        warp::path!("api" / "v1" / "slack.interact")
+            .and(warp::post())
+            .and(warp::header::<String>("x-slack-request-timestamp"))
+            .and(warp::header::<String>("x-slack-signature"))
+            .and(warp::body::bytes())
+            .and(warp::any().map(move || signing_secret.clone()))
+            .and(warp::any().map(move || approvers.clone()))
+            .and(warp::any().map(move || client.clone()))
+            .and(warp::any().map(move || workflow_controller.clone()))
+            .and_then(handlers::interact)
     }
 
     fn with_service_registry(
@@ -36,144 +132,1306 @@ pub mod filters {
     {
         warp::any().map(move || registry.clone())
     }
+
+    fn with_state_store(
+        store: StateStoreRef,
+    ) -> impl Filter<Extract = (StateStoreRef,), Error = std::convert::Infallible> + Clone {
+        warp::any().map(move || store.clone())
+    }
 }
 
 mod handlers {
+    use super::filters::Settings;
     use super::models;
-    use crate::services::{Notification, ServiceRegistryRef};
-    use crate::templates::TemplateRegistryRef;
+    use crate::deliveries::{DeliveryQueueRef, DeliveryStatus};
+    use crate::period::Period;
+    use crate::secrets::{self, Secret};
+    use crate::services::{Delivery, Notification, Service, ServiceRegistryRef};
+    use crate::state::{Scope, ServiceState, StateStoreRef};
+    use crate::templates::{Template, TemplateRegistryRef};
+    use crate::throttling::LimiterRef;
+    use crate::windows::Outside;
+    use crate::workflows::WorkflowControllerRef;
+    use futures::future::join_all;
+    use handlebars::Handlebars;
+    use k8s_openapi::chrono::{DateTime, Utc};
+    use ring::hmac;
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
     use std::convert::Infallible;
+    use std::future::Future;
+    use std::hash::{Hash, Hasher};
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    use warp::http::StatusCode;
+    use warp::hyper::body::Bytes;
+    use warp::Reply;
+
+    type CommandResult = Result<Delivery, String>;
 
-    type CommandResult = Result<String, String>;
+    /// The maximum age of a Slack request, protects against replay attacks
+    const MAX_REQUEST_AGE: u64 = 5 * 60;
+
+    /// How long Argo should wait before asking again about a notification delivered in the
+    /// background
+    const REQUEUE_AFTER: Duration = Duration::from_secs(5);
+
+    /// The longest Argo is asked to wait for a notification waiting for its delivery window, so
+    /// that a window opening earlier than expected is not missed for long
+    const MAX_REQUEUE_AFTER: Duration = Duration::from_secs(15 * 60);
+
+    /// The sub-template the services render their main message with
+    const PRIMARY_SUBTEMPLATE: &str = "primary";
+
+    /// The sub-template rendering the digest of a batch, in place of the primary one
+    const DIGEST_SUBTEMPLATE: &str = "digest";
 
     pub async fn dispatch(
-        input: models::Input,
+        body: Bytes,
         service_registry: ServiceRegistryRef,
         template_registry: TemplateRegistryRef,
-    ) -> Result<impl warp::Reply, Infallible> {
+        state_store: StateStoreRef,
+        settings: Settings,
+        queue: DeliveryQueueRef,
+        limiter: LimiterRef,
+    ) -> Result<warp::reply::Response, Infallible> {
+        // Argo only surfaces the message of a node, so the invalid requests are answered with a
+        // failed node rather than just an error status
+        let input = match models::Input::parse(&body) {
+            Ok(input) => input,
+            Err(models::InputError::NotHermes) => {
+                return Ok(StatusCode::NOT_FOUND.into_response());
+            }
+            Err(models::InputError::Malformed(e)) => {
+                return Ok(respond(Err(format!("Malformed request: {}", e))).into_response());
+            }
+            Err(models::InputError::Invalid(e)) => return Ok(respond(Err(e)).into_response()),
+        };
+        let workflow = input.workflow.map(|w| w.metadata);
+        let key = delivery_key(&workflow, &input.template);
+        let scope = workflow
+            .as_ref()
+            .map(|w| Scope {
+                uid: w.uid.clone(),
+                name: Some(w.name.clone()),
+            })
+            .unwrap_or_else(Scope::global);
         let result = match input.template.plugin.hermes {
             models::Command::Setup(models::CommandSetup { setup: config }) => {
-                setup(config, service_registry).await
+                setup(config, service_registry, state_store, scope).await
             }
-            models::Command::Notify(models::CommandNotify { notify: config }) => {
-                notify(config, service_registry, template_registry).await
+            models::Command::Notify(command) => {
+                let mut config = command.notify;
+                if let Some(workflow) = &workflow {
+                    let context = workflow.context(settings.argo_ui_url.as_deref());
+                    inject_context(&mut config.context, context);
+                }
+                let deliver = {
+                    let settings = settings.clone();
+                    let scope = scope.clone();
+                    move |config| {
+                        notify(
+                            config,
+                            settings,
+                            service_registry,
+                            template_registry,
+                            state_store,
+                            scope,
+                            limiter,
+                        )
+                    }
+                };
+                let opening = delayed_until(&config, &settings);
+                match condition_met(&config) {
+                    Ok(false) => Ok(Delivery::with_message(
+                        "Notification skipped: condition not met",
+                    )),
+                    Err(e) => Err(e),
+                    Ok(true) => match config.batch.take() {
+                        Some(batch) => {
+                            let reply = batch_notify(key, batch, config, &scope, queue, deliver);
+                            return Ok(reply.into_response());
+                        }
+                        // A delayed notification stays tracked after its window has opened, until
+                        // its outcome has been reported, so the following requests are answered
+                        // with that outcome rather than sending the notification again
+                        None if config.asynchronous
+                            || opening.is_some()
+                            || queue.status(&key).is_some() =>
+                        {
+                            let requeue = requeue_after(opening);
+                            config.asynchronous = true;
+                            let reply = deliver_async(key, queue, requeue, deliver(config));
+                            return Ok(reply.into_response());
+                        }
+                        None => deliver(config).await,
+                    },
+                }
             }
         };
-        let response = result
-            .map(|m| {
+        Ok(respond(result).into_response())
+    }
+
+    /// Rejects the requests that failed to authenticate
+    #[derive(Debug)]
+    pub struct Forbidden;
+
+    impl warp::reject::Reject for Forbidden {}
+
+    /// Answers the requests rejected with `Forbidden`, leaving the other rejections to the
+    /// remaining routes
+    pub async fn forbidden(
+        rejection: warp::Rejection,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        if rejection.find::<Forbidden>().is_some() {
+            Ok(warp::reply::with_status(
+                "Invalid plugin token",
+                StatusCode::FORBIDDEN,
+            ))
+        } else {
+            Err(rejection)
+        }
+    }
+
+    fn respond(result: CommandResult) -> warp::reply::Json {
+        // The messages end up in the UI of Argo, so make sure that no secret leaks through them
+        result
+            .map(|d| {
                 warp::reply::json(&models::Response {
                     node: models::Node {
                         phase: "Succeeded".into(),
-                        message: m,
+                        message: secrets::scrub(&d.message.unwrap_or_default()),
+                        outputs: models::Outputs::from_delivery(d.outputs),
                     },
+                    requeue: None,
                 })
             })
             .unwrap_or_else(|m| {
                 warp::reply::json(&models::Response {
                     node: models::Node {
                         phase: "Failed".into(),
-                        message: m,
+                        message: secrets::scrub(&m),
+                        outputs: None,
                     },
+                    requeue: None,
                 })
-            });
-        Ok(response)
+            })
+    }
+
+    /// Identifies the node a request has been issued for
+    ///
+    /// Argo does not pass the ID of the node to the plugins, so the node is identified by its
+    /// workflow and its template, which holds the already resolved inputs of the node.
+    fn delivery_key(
+        workflow: &Option<models::WorkflowMetadata>,
+        template: &models::Template,
+    ) -> String {
+        let mut hasher = DefaultHasher::new();
+        serde_json::to_string(template)
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        let uid = workflow
+            .as_ref()
+            .map(|w| w.uid.as_str())
+            .unwrap_or("global");
+        format!("{}/{:x}", uid, hasher.finish())
+    }
+
+    /// Delivers a notification in the background, letting Argo ask about the outcome later on
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Identifies the node the notification is delivered for
+    /// * `queue` - Tracks the background deliveries
+    /// * `requeue` - How long Argo should wait before asking again about the delivery
+    /// * `delivery` - Delivers the notification, only polled if the node has no delivery yet
+    fn deliver_async<F>(
+        key: String,
+        queue: DeliveryQueueRef,
+        requeue: Duration,
+        delivery: F,
+    ) -> warp::reply::Json
+    where
+        F: Future<Output = CommandResult> + Send + 'static,
+    {
+        match queue.enqueue(&key) {
+            Some(DeliveryStatus::Done(result)) => respond(result),
+            Some(DeliveryStatus::Pending) => running(requeue),
+            None => {
+                tokio::spawn(async move {
+                    let result = delivery.await;
+                    queue.complete(&key, result);
+                });
+                running(requeue)
+            }
+        }
+    }
+
+    fn running(requeue: Duration) -> warp::reply::Json {
+        warp::reply::json(&models::Response {
+            node: models::Node {
+                phase: "Running".into(),
+                message: "Notification queued".into(),
+                outputs: None,
+            },
+            requeue: Some(Period(requeue).to_string()),
+        })
+    }
+
+    /// Decides how long Argo should wait before asking again about a notification delivered in
+    /// the background
+    ///
+    /// # Arguments
+    ///
+    /// * `opening` - The time the notification waits for, if it is delayed
+    fn requeue_after(opening: Option<DateTime<Utc>>) -> Duration {
+        opening
+            .and_then(|o| (o - Utc::now()).to_std().ok())
+            .unwrap_or(REQUEUE_AFTER)
+            .clamp(REQUEUE_AFTER, MAX_REQUEUE_AFTER)
+    }
+
+    /// Makes the workflow metadata available to the templates under `hermes.workflow`
+    fn inject_context(context: &mut serde_json::Value, workflow: serde_json::Value) {
+        if context.is_null() {
+            *context = serde_json::json!({});
+        }
+        if let Some(context) = context.as_object_mut() {
+            let hermes = context
+                .entry("hermes")
+                .or_insert_with(|| serde_json::json!({}));
+            if !hermes.is_object() {
+                *hermes = serde_json::json!({});
+            }
+            hermes["workflow"] = workflow;
+        }
     }
 
     async fn setup(
         config: models::ServiceConfig,
         service_registry: ServiceRegistryRef,
+        state_store: StateStoreRef,
+        scope: Scope,
     ) -> CommandResult {
         service_registry
-            .setup(&config.alias, &config.service, config.config)
+            .setup(
+                &scope,
+                &config.alias,
+                &config.service,
+                config.config.clone(),
+            )
             .await
-            .map(|()| "Service setup successful".into())
-            .map_err(|e| e.to_string())
+            .map_err(|e| e.to_string())?;
+        let state = ServiceState {
+            service: config.service,
+            config: config.config,
+            data: serde_json::Value::Null,
+        };
+        state_store
+            .put(&scope, &config.alias, state)
+            .await
+            .map_err(|e| format!("Failed to persist the service instance: {}", e))?;
+        Ok(Delivery::with_message("Service setup successful"))
+    }
+
+    /// Retrieves the service instance of a workflow
+    ///
+    /// An instance missing from the registry is set up from the state store, i.e. because Hermes
+    /// has been restarted since the setup, or from a preconfigured service instance. In the latter
+    /// case every workflow gets its own instance, so that the workflows do not share their
+    /// message state.
+    async fn get_service(
+        alias: &str,
+        service_registry: &ServiceRegistryRef,
+        state_store: &StateStoreRef,
+        scope: &Scope,
+    ) -> Result<Arc<dyn Service>, String> {
+        if let Some(service) = service_registry.get(scope, alias) {
+            return Ok(service);
+        }
+        let stored_state = state_store
+            .get(scope, alias)
+            .await
+            .map_err(|e| format!("Failed to restore service instance \"{}\": {}", alias, e))?;
+        let state = match stored_state {
+            Some(state) => state,
+            None => {
+                let definition = service_registry
+                    .definition(&Scope::global(), alias)
+                    .ok_or_else(|| format!("Service instance \"{}\" not found", alias))?;
+                let state = ServiceState {
+                    service: definition.service,
+                    config: definition.config,
+                    data: serde_json::Value::Null,
+                };
+                state_store
+                    .put(scope, alias, state.clone())
+                    .await
+                    .map_err(|e| format!("Failed to persist the service instance: {}", e))?;
+                state
+            }
+        };
+        service_registry
+            .setup(scope, alias, &state.service, state.config)
+            .await
+            .map_err(|e| format!("Failed to restore service instance \"{}\": {}", alias, e))?;
+        let service = service_registry
+            .get(scope, alias)
+            .ok_or_else(|| format!("Service instance \"{}\" not found", alias))?;
+        service
+            .restore(state.data)
+            .map_err(|e| format!("Failed to restore service instance \"{}\": {}", alias, e))?;
+        Ok(service)
     }
 
     async fn notify(
         config: models::NotificationConfig,
+        settings: Settings,
         service_registry: ServiceRegistryRef,
         template_registry: TemplateRegistryRef,
+        state_store: StateStoreRef,
+        scope: Scope,
+        limiter: LimiterRef,
     ) -> CommandResult {
-        let template = template_registry
-            .get(&config.template)
-            .await
-            .map_err(|e| format!("Template retrieval failed: {}", e))?;
-        let service = service_registry
-            .get(&config.target)
-            .ok_or_else(|| format!("Service instance \"{}\" not found", config.target))?;
-        service
-            .notify(
-                config.config,
-                Notification {
-                    template,
-                    context: config.context,
-                },
+        let targets = config.targets(&settings.routes)?;
+        let now = Utc::now();
+        if config.targets.is_empty() && targets.len() == 1 {
+            // A single target reports its outcome as is
+            let target = targets.into_iter().next().unwrap_or_default();
+            return deliver_planned(
+                plan(target, &config, &settings, now),
+                &config,
+                &service_registry,
+                &template_registry,
+                &state_store,
+                &scope,
+                &limiter,
             )
+            .await;
+        }
+
+        let plans = targets.into_iter().map(|target| {
+            let alias = target.target.clone();
+            (alias, plan(target, &config, &settings, now))
+        });
+        let results = join_all(plans.map(|(alias, plan)| async {
+            let result = deliver_planned(
+                plan,
+                &config,
+                &service_registry,
+                &template_registry,
+                &state_store,
+                &scope,
+                &limiter,
+            )
+            .await;
+            (alias, result)
+        }))
+        .await;
+
+        let failures = results.iter().filter(|(_, r)| r.is_err()).count();
+        let mut delivery = Delivery::default();
+        let mut messages = vec![];
+        for (alias, result) in results {
+            match result {
+                Ok(d) => {
+                    messages.push(format!("{}: {}", alias, d.message.unwrap_or_default()));
+                    for (name, value) in d.outputs {
+                        delivery = delivery.output(&format!("{}-{}", alias, name), value);
+                    }
+                }
+                Err(e) => messages.push(format!("{}: {}", alias, e)),
+            }
+        }
+        let message = messages.join("; ");
+        let failed = match config.policy {
+            models::FailurePolicy::FailAny => failures > 0,
+            models::FailurePolicy::FailAll => failures == messages.len(),
+            models::FailurePolicy::BestEffort => false,
+        };
+        if failed {
+            return Err(message);
+        }
+        delivery.message = Some(message);
+        Ok(delivery)
+    }
+
+    /// Evaluates the `when` condition of a notification, if any
+    ///
+    /// The condition is a Handlebars template, rendered against the notification context. It is
+    /// considered false when it renders to nothing, `false` or `0`.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The notification, e.g. with `when: '{{eq status "Failed"}}'`
+    fn condition_met(config: &models::NotificationConfig) -> Result<bool, String> {
+        let condition = match &config.when {
+            Some(condition) => condition,
+            None => return Ok(true),
+        };
+        let mut handlebars = Handlebars::new();
+        handlebars.set_strict_mode(true);
+        let rendered = handlebars
+            .render_template(condition, &config.context)
+            .map_err(|e| format!("Failed to evaluate `when`: {}", e))?;
+        Ok(!matches!(rendered.trim(), "" | "false" | "0"))
+    }
+
+    /// Adds a notification to a batch, delivered as a single digest once the batch window closes
+    ///
+    /// The node that opens the batch keeps running until the digest is delivered, so that it
+    /// reports the outcome of the delivery. The other nodes of the batch succeed right away.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Identifies the node the notification is sent for
+    /// * `batch` - The batch to add the notification to
+    /// * `config` - The notification, used to deliver the digest when it opens the batch
+    /// * `scope` - The workflow the batch belongs to
+    /// * `queue` - Tracks the background deliveries and buffers the batches
+    /// * `deliver` - Delivers the digest
+    fn batch_notify<D, F>(
+        key: String,
+        batch: models::BatchConfig,
+        mut config: models::NotificationConfig,
+        scope: &Scope,
+        queue: DeliveryQueueRef,
+        deliver: D,
+    ) -> warp::reply::Json
+    where
+        D: FnOnce(models::NotificationConfig) -> F + Send + 'static,
+        F: Future<Output = CommandResult> + Send + 'static,
+    {
+        // Argo keeps asking about the node that opened the batch, and might ask again about the
+        // other ones too
+        match queue.status(&key) {
+            Some(DeliveryStatus::Done(result)) => return respond(result),
+            Some(DeliveryStatus::Pending) => return running(REQUEUE_AFTER),
+            None => {}
+        }
+        let batch_key = format!("{}/{}", scope.uid, batch.key);
+        let window = batch.window.0;
+        let size = queue.add_to_batch(&batch_key, window, config.context.clone());
+        if size > 1 {
+            let result = Ok(Delivery::with_message(format!(
+                "Notification added to batch \"{}\" ({} so far)",
+                batch.key, size
+            )));
+            queue.report(&key, result.clone());
+            return respond(result);
+        }
+        deliver_async(key, queue.clone(), REQUEUE_AFTER, async move {
+            tokio::time::sleep(window).await;
+            config.context = digest_context(queue.take_batch(&batch_key));
+            config.digest = true;
+            config.asynchronous = true;
+            deliver(config).await
+        })
+    }
+
+    /// Builds the context of a digest from the contexts of the batched notifications
+    ///
+    /// The workflow metadata stays available under the `hermes` key, as the notifications of a
+    /// batch all come from the same workflow.
+    fn digest_context(contexts: Vec<serde_json::Value>) -> serde_json::Value {
+        let hermes = contexts
+            .first()
+            .and_then(|c| c.get("hermes"))
+            .cloned()
+            .unwrap_or_else(|| serde_json::json!({}));
+        serde_json::json!({
+            "count": contexts.len(),
+            "notifications": contexts,
+            "hermes": hermes,
+        })
+    }
+
+    /// Builds the template of a digest, in which the `digest` sub-template takes the place of the
+    /// `primary` one
+    fn digest_template(template: &Template) -> Result<Template, String> {
+        let digest = template
+            .get(DIGEST_SUBTEMPLATE)
+            .ok_or("Template is missing the `digest` sub-template")?;
+        Ok([(PRIMARY_SUBTEMPLATE.to_string(), digest.clone())]
+            .into_iter()
+            .collect())
+    }
+
+    /// Identifies the duplicates of a notification
+    ///
+    /// The notification is identified by the rendered `key` of the dedup config or, by default, by
+    /// all of its rendered sub-templates.
+    ///
+    /// # Arguments
+    ///
+    /// * `dedup` - The dedup config of the notification
+    /// * `notification` - The notification to identify
+    /// * `alias` - The alias of the service instance to notify
+    /// * `template` - The name of the template of the notification
+    /// * `scope` - The workflow the notification is sent from
+    fn dedup_key(
+        dedup: &models::DedupConfig,
+        notification: &Notification,
+        alias: &str,
+        template: &str,
+        scope: &Scope,
+    ) -> Result<String, String> {
+        let mut hasher = DefaultHasher::new();
+        match &dedup.key {
+            Some(key) => {
+                let mut handlebars = Handlebars::new();
+                handlebars.set_strict_mode(true);
+                handlebars
+                    .render_template(key, &notification.context)
+                    .map_err(|e| format!("Failed to render `dedup.key`: {}", e))?
+                    .hash(&mut hasher);
+            }
+            None => {
+                let mut subtemplates: Vec<_> = notification.template.keys().collect();
+                subtemplates.sort();
+                for subtemplate in subtemplates {
+                    subtemplate.hash(&mut hasher);
+                    notification
+                        .render(subtemplate)
+                        .map_err(|e| e.to_string())
+                        .hash(&mut hasher);
+                }
+            }
+        }
+        Ok(format!(
+            "{}/{}/{}/{:x}",
+            scope.uid,
+            alias,
+            template,
+            hasher.finish()
+        ))
+    }
+
+    /// Returns the time the last of the targets of a notification waiting for its delivery
+    /// window can be notified at, if any target has to wait
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The notification
+    /// * `settings` - The settings holding the routes and the delivery windows
+    fn delayed_until(
+        config: &models::NotificationConfig,
+        settings: &Settings,
+    ) -> Option<DateTime<Utc>> {
+        let now = Utc::now();
+        let targets = config.targets(&settings.routes).unwrap_or_default();
+        targets
+            .iter()
+            .filter_map(|target| {
+                config
+                    .window
+                    .as_ref()
+                    .or_else(|| settings.windows.get(&target.target))
+            })
+            .filter(|w| matches!(w.outside, Outside::Delay) && !w.is_open(now))
+            .filter_map(|w| w.next_opening(now))
+            .max()
+    }
+
+    /// How a notification is delivered to a target, according to the delivery window of the
+    /// target
+    enum Plan {
+        /// Right away
+        Now(models::TargetConfig),
+        /// To the fallback of the target, as its window is closed
+        Downgrade(models::TargetConfig),
+        /// Once the window of the target opens
+        Later(models::TargetConfig, DateTime<Utc>),
+        /// Not at all, as the window of the target is closed
+        Drop,
+    }
+
+    /// Decides how to deliver a notification to a target
+    ///
+    /// The window given by the notification takes precedence over the window of the target.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The service instance to notify and its config
+    /// * `config` - The notification
+    /// * `settings` - The settings holding the delivery windows of the service instances
+    /// * `now` - The time the notification is sent at
+    fn plan(
+        target: models::TargetConfig,
+        config: &models::NotificationConfig,
+        settings: &Settings,
+        now: DateTime<Utc>,
+    ) -> Plan {
+        let window = config
+            .window
+            .as_ref()
+            .or_else(|| settings.windows.get(&target.target));
+        let window = match window {
+            Some(window) if !window.is_open(now) => window,
+            _ => return Plan::Now(target),
+        };
+        match &window.outside {
+            Outside::Drop => Plan::Drop,
+            Outside::Delay => match window.next_opening(now) {
+                // Only the notifications delivered in the background can wait, which is what the
+                // delayed ones are turned into. The window might have opened in the meantime
+                Some(opening) if config.asynchronous => Plan::Later(target, opening),
+                Some(_) => Plan::Now(target),
+                None => Plan::Drop,
+            },
+            Outside::Downgrade(fallback) => Plan::Downgrade(models::TargetConfig {
+                target: fallback.target.clone(),
+                config: fallback.config.clone().unwrap_or(target.config),
+                template: target.template,
+            }),
+        }
+    }
+
+    /// Delivers a notification to a target as planned
+    ///
+    /// # Arguments
+    ///
+    /// * `plan` - How to deliver the notification
+    /// * `config` - The notification
+    async fn deliver_planned(
+        plan: Plan,
+        config: &models::NotificationConfig,
+        service_registry: &ServiceRegistryRef,
+        template_registry: &TemplateRegistryRef,
+        state_store: &StateStoreRef,
+        scope: &Scope,
+        limiter: &LimiterRef,
+    ) -> CommandResult {
+        let (target, downgraded) = match plan {
+            Plan::Drop => {
+                return Ok(Delivery::with_message(
+                    "Notification dropped outside the delivery window",
+                ))
+            }
+            Plan::Now(target) => (target, false),
+            Plan::Downgrade(target) => (target, true),
+            Plan::Later(target, opening) => {
+                if let Ok(delay) = (opening - Utc::now()).to_std() {
+                    tokio::time::sleep(delay).await;
+                }
+                (target, false)
+            }
+        };
+        let alias = target.target.clone();
+        let result = notify_target(
+            target,
+            config,
+            service_registry,
+            template_registry,
+            state_store,
+            scope,
+            limiter,
+        )
+        .await;
+        if !downgraded {
+            return result;
+        }
+        result
+            .map(|d| Delivery {
+                message: d
+                    .message
+                    .map(|m| format!("Downgraded to \"{}\": {}", alias, m)),
+                ..d
+            })
+            .map_err(|e| format!("Downgraded to \"{}\": {}", alias, e))
+    }
+
+    /// Sends a notification to a single service instance
+    ///
+    /// The duplicates of the recently sent notifications and the notifications exceeding the
+    /// throttling limit are suppressed rather than sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The service instance to notify and its config
+    /// * `config` - The notification, with the template to use unless the target overrides it
+    async fn notify_target(
+        target: models::TargetConfig,
+        config: &models::NotificationConfig,
+        service_registry: &ServiceRegistryRef,
+        template_registry: &TemplateRegistryRef,
+        state_store: &StateStoreRef,
+        scope: &Scope,
+        limiter: &LimiterRef,
+    ) -> CommandResult {
+        let template_name = target.template.as_deref().unwrap_or(&config.template);
+        let mut template = template_registry
+            .get(template_name)
             .await
-            .map(|_| "Notification sent".into())
+            .map_err(|e| format!("Template retrieval failed: {}", e))?;
+        if config.digest {
+            template = Arc::new(digest_template(&template)?);
+        }
+        let service = get_service(&target.target, service_registry, state_store, scope).await?;
+        let notification = Notification {
+            template,
+            context: config.context.clone(),
+        };
+
+        let dedup_key = match &config.dedup {
+            Some(dedup) => {
+                let key = dedup_key(dedup, &notification, &target.target, template_name, scope)?;
+                if let Some(suppressed) = limiter.deduplicate(&key, dedup.window.0) {
+                    return Ok(Delivery::with_message(format!(
+                        "Notification suppressed: duplicate within {} ({} suppressed)",
+                        dedup.window, suppressed
+                    )));
+                }
+                Some(key)
+            }
+            None => None,
+        };
+        if let Err(throttle) = limiter.throttle(&target.target) {
+            if let Some(key) = &dedup_key {
+                limiter.forget(key);
+            }
+            return Ok(Delivery::with_message(format!(
+                "Notification suppressed: more than {} per {}",
+                throttle.limit, throttle.per
+            )));
+        }
+
+        let result = service.notify(target.config, notification).await;
+        if let (Err(_), Some(key)) = (&result, &dedup_key) {
+            // Let the failed notification be retried
+            limiter.forget(key);
+        }
+        // Persist the message state even after a failure, as some of the messages might have been
+        // sent already
+        let persisted = match state_store.get(scope, &target.target).await {
+            Ok(Some(mut state)) => {
+                state.data = service.state();
+                state_store.put(scope, &target.target, state).await
+            }
+            Ok(None) => Ok(()),
+            Err(e) => Err(e),
+        };
+        // The notification has been sent regardless, so a failure to persist its state is only
+        // reported rather than failing the node
+        let warning = persisted
+            .err()
+            .map(|e| format!(" (failed to persist the service state: {})", e))
+            .unwrap_or_default();
+        result
+            .map(|d| Delivery {
+                message: Some(d.message.unwrap_or_else(|| "Notification sent".into()) + &warning),
+                ..d
+            })
+            .map_err(|e| e.to_string() + &warning)
+    }
+
+    pub async fn interact(
+        timestamp: String,
+        signature: String,
+        body: Bytes,
+        signing_secret: Secret,
+        approvers: Option<Arc<HashSet<String>>>,
+        client: reqwest::Client,
+        workflow_controller: WorkflowControllerRef,
+    ) -> Result<impl warp::Reply, Infallible> {
+        if !verify_signature(signing_secret.expose(), &timestamp, &signature, &body) {
+            return Ok(warp::reply::with_status(
+                "Invalid signature".into(),
+                StatusCode::UNAUTHORIZED,
+            ));
+        }
+        let interaction = serde_urlencoded::from_bytes::<models::InteractionForm>(&body)
             .map_err(|e| e.to_string())
+            .and_then(|f| {
+                serde_json::from_str::<models::Interaction>(&f.payload).map_err(|e| e.to_string())
+            });
+        let interaction = match interaction {
+            Ok(interaction) => interaction,
+            Err(e) => {
+                return Ok(warp::reply::with_status(
+                    format!("Invalid payload: {}", e),
+                    StatusCode::BAD_REQUEST,
+                ))
+            }
+        };
+        // Ignore the interactions with elements that Hermes does not own
+        let action = interaction
+            .actions
+            .iter()
+            .find_map(|a| models::WorkflowAction::from_interaction(a).map(|w| (a, w)));
+        if let Some((action, workflow_action)) = action {
+            let workflow = action.value.as_deref().unwrap_or_default();
+            let approved = approvers
+                .as_ref()
+                .is_none_or(|a| a.contains(&interaction.user.id));
+            let response = if approved {
+                let result = match workflow_action {
+                    models::WorkflowAction::Resume => workflow_controller.resume(workflow).await,
+                    models::WorkflowAction::Stop => workflow_controller.stop(workflow).await,
+                };
+                match result {
+                    Ok(()) => {
+                        let note = format!(
+                            "{} by <@{}>",
+                            workflow_action.description(),
+                            interaction.user.id
+                        );
+                        interaction.message.map(|m| m.resolve(&note))
+                    }
+                    Err(e) => Some(ephemeral(&format!(
+                        "Failed to control workflow \"{}\": {}",
+                        workflow, e
+                    ))),
+                }
+            } else {
+                Some(ephemeral(&format!(
+                    "You are not allowed to control workflow \"{}\"",
+                    workflow
+                )))
+            };
+            if let (Some(response_url), Some(response)) = (interaction.response_url, response) {
+                // Slack has already been given the final say about the outcome, so there is not
+                // much to do about a failed update
+                let _ = client.post(response_url).json(&response).send().await;
+            }
+        }
+        Ok(warp::reply::with_status(String::new(), StatusCode::OK))
+    }
+
+    /// Builds a response to an interaction visible only to the user who interacted
+    fn ephemeral(text: &str) -> serde_json::Value {
+        serde_json::json!({
+            "response_type": "ephemeral",
+            "replace_original": false,
+            "text": secrets::scrub(text),
+        })
+    }
+
+    /// Verifies that a request has been sent by Slack
+    ///
+    /// See https://api.slack.com/authentication/verifying-requests-from-slack
+    fn verify_signature(secret: &str, timestamp: &str, signature: &str, body: &[u8]) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        match timestamp.parse::<u64>() {
+            Ok(ts) if now.abs_diff(ts) <= MAX_REQUEST_AGE => (),
+            _ => return false,
+        }
+        let signature = match signature.strip_prefix("v0=").and_then(decode_hex) {
+            Some(signature) => signature,
+            None => return false,
+        };
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+        let mut message = format!("v0:{}:", timestamp).into_bytes();
+        message.extend_from_slice(body);
+        hmac::verify(&key, &message, &signature).is_ok()
+    }
+
+    fn decode_hex(s: &str) -> Option<Vec<u8>> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| s.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+            .collect()
     }
 }
 
 mod models {
+    use crate::period::Period;
+    use crate::routing::Routes;
+    use crate::windows::DeliveryWindow;
+    use serde::de::DeserializeOwned;
     use serde::{Deserialize, Serialize};
+    use std::collections::{BTreeMap, HashMap};
 
     #[derive(Debug, Serialize)]
     pub struct Response {
         pub node: Node,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub requeue: Option<String>,
     }
 
     #[derive(Debug, Serialize)]
     pub struct Node {
         pub phase: String,
         pub message: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub outputs: Option<Outputs>,
     }
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Serialize)]
+    pub struct Outputs {
+        pub parameters: Vec<Parameter>,
+    }
+
+    impl Outputs {
+        /// Converts the outputs of a delivery into output parameters, if there are any
+        pub fn from_delivery(outputs: BTreeMap<String, String>) -> Option<Self> {
+            if outputs.is_empty() {
+                return None;
+            }
+            let parameters = outputs
+                .into_iter()
+                .map(|(name, value)| Parameter { name, value })
+                .collect();
+            Some(Self { parameters })
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct Parameter {
+        pub name: String,
+        pub value: String,
+    }
+
+    #[derive(Debug)]
     pub struct Input {
+        pub workflow: Option<Workflow>,
         pub template: Template,
     }
 
+    #[derive(Debug)]
+    pub enum InputError {
+        /// The request is not valid JSON
+        Malformed(String),
+        /// The request is meant for another executor plugin
+        NotHermes,
+        /// The request is meant for Hermes, but it is invalid
+        Invalid(String),
+    }
+
+    impl Input {
+        /// Parses a plugin request
+        ///
+        /// The commands are told apart by their keys, rather than by trying them one after
+        /// another, so that the errors can point at the offending field.
+        ///
+        /// # Arguments
+        ///
+        /// * `body` - The body of the request
+        pub fn parse(body: &[u8]) -> Result<Self, InputError> {
+            let body: serde_json::Value =
+                serde_json::from_slice(body).map_err(|e| InputError::Malformed(e.to_string()))?;
+            let hermes = body
+                .pointer("/template/plugin/hermes")
+                .ok_or(InputError::NotHermes)?;
+            let path = "template.plugin.hermes";
+            let command = if hermes.get("setup").is_some() {
+                Command::Setup(field(hermes, path)?)
+            } else if hermes.get("notify").is_some() {
+                Command::Notify(Box::new(field(hermes, path)?))
+            } else {
+                return Err(InputError::Invalid(format!(
+                    "Invalid `{}`: expected either `setup` or `notify`",
+                    path
+                )));
+            };
+            let workflow = match body.get("workflow") {
+                None | Some(serde_json::Value::Null) => None,
+                Some(workflow) => Some(field(workflow, "workflow")?),
+            };
+            Ok(Self {
+                workflow,
+                template: Template {
+                    plugin: Plugin { hermes: command },
+                },
+            })
+        }
+    }
+
+    /// Deserializes a part of the request, reporting the full path of the offending field
+    fn field<T: DeserializeOwned>(value: &serde_json::Value, path: &str) -> Result<T, InputError> {
+        serde_path_to_error::deserialize(value.clone()).map_err(|e| {
+            let path = match e.path().to_string().as_str() {
+                "." => path.to_string(),
+                inner => format!("{}.{}", path, inner),
+            };
+            InputError::Invalid(format!("Invalid `{}`: {}", path, e.into_inner()))
+        })
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Workflow {
+        pub metadata: WorkflowMetadata,
+    }
+
     #[derive(Debug, Deserialize)]
+    pub struct WorkflowMetadata {
+        pub name: String,
+        #[serde(default)]
+        pub namespace: Option<String>,
+        pub uid: String,
+        #[serde(default)]
+        pub labels: HashMap<String, String>,
+        #[serde(default)]
+        pub annotations: HashMap<String, String>,
+    }
+
+    impl WorkflowMetadata {
+        /// Builds the template context describing the workflow
+        ///
+        /// # Arguments
+        ///
+        /// * `argo_ui_url` - Base URL of the Argo UI, used to build a link to the workflow
+        pub fn context(&self, argo_ui_url: Option<&str>) -> serde_json::Value {
+            let url = match (argo_ui_url, &self.namespace) {
+                (Some(base), Some(namespace)) => Some(format!(
+                    "{}/workflows/{}/{}",
+                    base.trim_end_matches('/'),
+                    namespace,
+                    self.name
+                )),
+                _ => None,
+            };
+            serde_json::json!({
+                "name": self.name,
+                "namespace": self.namespace,
+                "uid": self.uid,
+                "labels": self.labels,
+                "annotations": self.annotations,
+                "url": url,
+            })
+        }
+    }
+
+    #[derive(Debug, Serialize)]
     pub struct Template {
         pub plugin: Plugin,
     }
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Serialize)]
     pub struct Plugin {
         pub hermes: Command,
     }
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
+    #[serde(deny_unknown_fields)]
     pub struct ServiceConfig {
         pub alias: String,
         pub service: String,
         pub config: serde_json::Value,
     }
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
+    #[serde(deny_unknown_fields)]
     pub struct CommandSetup {
         pub setup: ServiceConfig,
     }
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
+    #[serde(deny_unknown_fields)]
     pub struct NotificationConfig {
-        pub target: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub target: Option<String>,
         pub template: String,
         pub context: serde_json::Value,
+        #[serde(default)]
         pub config: serde_json::Value,
+        /// Service instances to deliver the notification to concurrently, instead of `target`
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub targets: Vec<TargetConfig>,
+        /// Decides the outcome of the step when some of the `targets` fail
+        #[serde(default)]
+        pub policy: FailurePolicy,
+        /// A condition the notification is only delivered under
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub when: Option<String>,
+        /// Suppresses the duplicates of the recently sent notifications
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub dedup: Option<DedupConfig>,
+        /// The time the notification can be delivered in, overriding the windows of the targets
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub window: Option<DeliveryWindow>,
+        /// Aggregates the notifications of the same batch into a single digest
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub batch: Option<BatchConfig>,
+        /// Whether the notification is the digest of a batch
+        #[serde(skip)]
+        pub digest: bool,
+        /// Whether to deliver the notification in the background, letting Argo poll for the
+        /// outcome
+        #[serde(default, rename = "async")]
+        pub asynchronous: bool,
     }
 
-    #[derive(Debug, Deserialize)]
+    impl NotificationConfig {
+        /// Lists the service instances to deliver the notification to
+        ///
+        /// The notification is routed when neither `target` nor `targets` is given.
+        ///
+        /// # Arguments
+        ///
+        /// * `routes` - The rules to route the notification with
+        pub fn targets(&self, routes: &Routes) -> Result<Vec<TargetConfig>, String> {
+            match (&self.target, self.targets.is_empty()) {
+                (Some(target), true) => Ok(vec![TargetConfig {
+                    target: target.clone(),
+                    config: self.config.clone(),
+                    template: None,
+                }]),
+                (None, false) => Ok(self.targets.clone()),
+                (None, true) if !routes.is_empty() => {
+                    let targets: Vec<_> = routes
+                        .route(&self.context)
+                        .into_iter()
+                        .map(|route| TargetConfig {
+                            target: route.target.clone(),
+                            config: route.config.clone().unwrap_or_else(|| self.config.clone()),
+                            template: route.template.clone(),
+                        })
+                        .collect();
+                    if targets.is_empty() {
+                        return Err("No route matches the notification".into());
+                    }
+                    Ok(targets)
+                }
+                _ => Err("Exactly one of `target` and `targets` has to be given".into()),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Default, Deserialize, Serialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct TargetConfig {
+        pub target: String,
+        #[serde(default)]
+        pub config: serde_json::Value,
+        /// Overrides the template of the notification for this target
+        #[serde(default)]
+        pub template: Option<String>,
+    }
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct DedupConfig {
+        /// How long the duplicates are suppressed for
+        pub window: Period,
+        /// A Handlebars template rendering the key that identifies the duplicates, instead of the
+        /// whole rendered notification
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub key: Option<String>,
+    }
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct BatchConfig {
+        /// Identifies the batch within the workflow
+        pub key: String,
+        /// How long the batch collects the notifications for, after it has been opened
+        pub window: Period,
+    }
+
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+    #[serde(rename_all = "kebab-case")]
+    pub enum FailurePolicy {
+        /// The step fails if any of the targets fails
+        #[default]
+        FailAny,
+        /// The step fails only if all the targets fail
+        FailAll,
+        /// The step never fails, the failures are only reported in its message
+        BestEffort,
+    }
+
+    #[derive(Debug, Deserialize, Serialize)]
+    #[serde(deny_unknown_fields)]
     pub struct CommandNotify {
         pub notify: NotificationConfig,
     }
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Serialize)]
     #[serde(untagged)]
     pub enum Command {
         Setup(CommandSetup),
-        Notify(CommandNotify),
+        Notify(Box<CommandNotify>),
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct InteractionForm {
+        pub payload: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Interaction {
+        pub user: InteractionUser,
+        #[serde(default)]
+        pub actions: Vec<InteractionAction>,
+        pub message: Option<InteractionMessage>,
+        pub response_url: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct InteractionUser {
+        pub id: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct InteractionAction {
+        pub action_id: String,
+        pub value: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct InteractionMessage {
+        pub text: Option<String>,
+        #[serde(default)]
+        pub blocks: Vec<serde_json::Value>,
+    }
+
+    impl InteractionMessage {
+        /// Replaces the Hermes buttons in the message with a note about the taken action
+        pub fn resolve(self, note: &str) -> serde_json::Value {
+            let mut blocks: Vec<_> = self
+                .blocks
+                .into_iter()
+                .filter(|b| !WorkflowAction::is_actions_block(b))
+                .collect();
+            blocks.push(serde_json::json!({
+                "type": "context",
+                "elements": [{"type": "mrkdwn", "text": note}],
+            }));
+            serde_json::json!({
+                "replace_original": true,
+                "text": self.text,
+                "blocks": blocks,
+            })
+        }
+    }
+
+    /// An action that can be taken on a workflow using a Slack button
+    #[derive(Debug, Clone, Copy)]
+    pub enum WorkflowAction {
+        Resume,
+        Stop,
+    }
+
+    impl WorkflowAction {
+        pub const PREFIX: &'static str = "hermes.";
+
+        pub fn from_interaction(action: &InteractionAction) -> Option<Self> {
+            match action.action_id.strip_prefix(Self::PREFIX)? {
+                "resume" => Some(WorkflowAction::Resume),
+                "stop" => Some(WorkflowAction::Stop),
+                _ => None,
+            }
+        }
+
+        /// Checks whether a block holds any of the Hermes buttons
+        pub fn is_actions_block(block: &serde_json::Value) -> bool {
+            block["type"] == "actions"
+                && block["elements"].as_array().into_iter().flatten().any(|e| {
+                    e["action_id"]
+                        .as_str()
+                        .is_some_and(|id| id.starts_with(Self::PREFIX))
+                })
+        }
+
+        pub fn description(&self) -> &'static str {
+            match self {
+                WorkflowAction::Resume => ":white_check_mark: Approved",
+                WorkflowAction::Stop => ":no_entry: Stopped",
+            }
+        }
     }
 }
@@ -1,13 +1,17 @@
+use crate::state::Scope;
+use crate::throttling::Throttle;
+use crate::windows::DeliveryWindow;
 use as_any::AsAny;
 use async_trait::async_trait;
 use handlebars::Handlebars;
-use std::collections::HashMap;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 
-mod slack;
+pub mod slack;
 
 #[derive(Debug)]
 pub enum FactoryError {
@@ -42,6 +46,37 @@ impl fmt::Display for CallError {
     }
 }
 
+/// The outcome of a successfully sent notification
+#[derive(Debug, Clone, Default)]
+pub struct Delivery {
+    /// Describes the outcome when it differs from a plain delivery, e.g. the notification was
+    /// scheduled instead of sent right away
+    pub message: Option<String>,
+    /// Values identifying the sent notification, e.g. a message ID, exposed to the workflow as
+    /// output parameters of the step
+    pub outputs: BTreeMap<String, String>,
+}
+
+impl Delivery {
+    pub fn with_message(message: impl Into<String>) -> Self {
+        Self {
+            message: Some(message.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Adds an output parameter
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the output parameter
+    /// * `value` - The value of the output parameter
+    pub fn output(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.outputs.insert(name.into(), value.into());
+        self
+    }
+}
+
 pub struct Notification {
     /// A raw notification template
     pub template: Arc<HashMap<String, String>>,
@@ -111,7 +146,21 @@ pub trait Service: Sync + Send + AsAny {
         &self,
         config: serde_json::Value,
         notification: Notification,
-    ) -> Result<(), CallError>;
+    ) -> Result<Delivery, CallError>;
+
+    /// Exports the state that has to survive a restart of Hermes, i.e. IDs of the sent messages
+    fn state(&self) -> serde_json::Value {
+        serde_json::Value::Null
+    }
+
+    /// Restores the state previously exported using `state`
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - The exported state
+    fn restore(&self, _state: serde_json::Value) -> Result<(), CallError> {
+        Ok(())
+    }
 }
 
 /// Registry of active service instances
@@ -125,39 +174,120 @@ pub trait ServiceRegistry: Sync + Send {
     ///
     /// # Arguments
     ///
+    /// * `scope` - The workflow the service instance belongs to. Instances set up in the global
+    /// scope are available to all the workflows
     /// * `alias` - Alias to assign to the service instance. This allows for having multiple
     /// instances of a single service
     /// * `service_name` - Name of the service to instantiate, i.e. slack, teams
     /// * `config` - Service config with a dynamic shape. To be validated by a service factory
     async fn setup(
         &self,
+        scope: &Scope,
         alias: &str,
         service_name: &str,
         config: serde_json::Value,
     ) -> Result<(), FactoryError>;
 
-    /// Retrieves a service instance
+    /// Retrieves a service instance set up in the given scope
     ///
     /// # Arguments
     ///
+    /// * `scope` - The workflow the service instance belongs to
     /// * `alias` - The alias of a service instance to retrieve
-    fn get(&self, alias: &str) -> Option<Arc<dyn Service>>;
+    fn get(&self, scope: &Scope, alias: &str) -> Option<Arc<dyn Service>>;
+
+    /// Retrieves the definition a service instance has been set up with
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - The workflow the service instance belongs to
+    /// * `alias` - The alias of the service instance
+    fn definition(&self, scope: &Scope, alias: &str) -> Option<ServiceDefinition>;
 }
 
 pub type ServiceRegistryRef = Arc<dyn ServiceRegistry>;
 
+/// A service instance defined upfront, rather than by a setup step of a workflow
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServiceDefinition {
+    /// Name of the service to instantiate, i.e. slack
+    pub service: String,
+    /// Service config, the same as passed to a setup step
+    pub config: serde_json::Value,
+    /// The time the notifications can be delivered to the service instance in. Enforced by the
+    /// plugin API rather than the registry
+    #[serde(default)]
+    pub window: Option<DeliveryWindow>,
+    /// Limits the rate of the notifications sent by the service instance, across all the
+    /// workflows. Enforced by the plugin API rather than the registry
+    #[serde(default)]
+    pub throttle: Option<Throttle>,
+}
+
+/// Service definitions keyed by their aliases
+pub type ServiceDefinitions = HashMap<String, ServiceDefinition>;
+
+/// Parses service definitions from a YAML (or JSON) document mapping aliases to definitions
+///
+/// # Arguments
+///
+/// * `source` - The document to parse
+pub fn parse_definitions(source: &str) -> Result<ServiceDefinitions, String> {
+    serde_yaml::from_str(source).map_err(|e| format!("Invalid service definitions: {}", e))
+}
+
+/// Sets up all the given service instances in the global scope, so that workflows can use them
+/// without a setup step
+///
+/// # Arguments
+///
+/// * `registry` - The registry to set up the service instances in
+/// * `definitions` - The service instances to set up
+pub async fn setup_all(
+    registry: &ServiceRegistryRef,
+    definitions: ServiceDefinitions,
+) -> Result<(), String> {
+    for (alias, definition) in definitions {
+        registry
+            .setup(
+                &Scope::global(),
+                &alias,
+                &definition.service,
+                definition.config,
+            )
+            .await
+            .map_err(|e| format!("Failed to set up service instance \"{}\": {}", alias, e))?;
+    }
+    Ok(())
+}
+
 pub mod registries {
-    use super::{slack, FactoryError, Service, ServiceFactory, ServiceFactoryFn, ServiceRegistry};
+    use super::{
+        slack, FactoryError, Service, ServiceDefinition, ServiceFactory, ServiceFactoryFn,
+        ServiceRegistry,
+    };
+    use crate::state::{Scope, IDLE_TIMEOUT};
     use async_trait::async_trait;
     use lazy_static::lazy_static;
     use parking_lot::Mutex;
     use std::collections::HashMap;
     use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    /// Identifies a service instance by the UID of its workflow and its alias
+    type InstanceKey = (String, String);
+
+    struct Instance {
+        service: Arc<dyn Service>,
+        definition: ServiceDefinition,
+        used_at: Instant,
+    }
 
     /// A registry of all the active service instances
     pub struct DefaultServiceRegistry {
         services: HashMap<String, Arc<ServiceFactoryFn>>,
-        instances: Arc<Mutex<HashMap<String, Arc<dyn Service>>>>,
+        instances: Arc<Mutex<HashMap<InstanceKey, Instance>>>,
+        idle_timeout: Duration,
     }
 
     impl DefaultServiceRegistry {
@@ -166,17 +296,42 @@ pub mod registries {
         }
 
         pub fn with_services(services: HashMap<String, Arc<ServiceFactoryFn>>) -> Arc<Self> {
+            Self::with_idle_timeout(services, IDLE_TIMEOUT)
+        }
+
+        /// Creates a registry evicting the service instances of the workflows after they have
+        /// not been used for the given time
+        ///
+        /// The instances set up globally are never evicted. An evicted instance is restored from
+        /// the state store once it is needed again.
+        ///
+        /// # Arguments
+        ///
+        /// * `services` - The available services, keyed by their names
+        /// * `idle_timeout` - How long an instance is kept after its last use
+        pub fn with_idle_timeout(
+            services: HashMap<String, Arc<ServiceFactoryFn>>,
+            idle_timeout: Duration,
+        ) -> Arc<Self> {
             Arc::from(Self {
                 services,
                 instances: Arc::new(Mutex::new(HashMap::new())),
+                idle_timeout,
             })
         }
+
+        fn evict(&self, instances: &mut HashMap<InstanceKey, Instance>) {
+            let global = Scope::global().uid;
+            instances
+                .retain(|(uid, _), i| *uid == global || i.used_at.elapsed() < self.idle_timeout);
+        }
     }
 
     #[async_trait]
     impl ServiceRegistry for DefaultServiceRegistry {
         async fn setup(
             &self,
+            scope: &Scope,
             alias: &str,
             service_name: &str,
             config: serde_json::Value,
@@ -185,14 +340,38 @@ pub mod registries {
                 .services
                 .get(service_name)
                 .ok_or(FactoryError::ServiceNotFound)?;
-            let service = factory(config).await?;
+            let service = factory(config.clone()).await?;
+            let instance = Instance {
+                service,
+                definition: ServiceDefinition {
+                    service: service_name.into(),
+                    config,
+                    window: None,
+                    throttle: None,
+                },
+                used_at: Instant::now(),
+            };
             let mut instances = self.instances.lock();
-            instances.insert(alias.into(), service);
+            self.evict(&mut instances);
+            instances.insert((scope.uid.clone(), alias.into()), instance);
             Ok(())
         }
 
-        fn get(&self, alias: &str) -> Option<Arc<dyn Service>> {
-            self.instances.lock().get(alias).cloned()
+        fn get(&self, scope: &Scope, alias: &str) -> Option<Arc<dyn Service>> {
+            let key = (scope.uid.clone(), alias.to_string());
+            let mut instances = self.instances.lock();
+            self.evict(&mut instances);
+            instances.get_mut(&key).map(|i| {
+                i.used_at = Instant::now();
+                i.service.clone()
+            })
+        }
+
+        fn definition(&self, scope: &Scope, alias: &str) -> Option<ServiceDefinition> {
+            let key = (scope.uid.clone(), alias.to_string());
+            let mut instances = self.instances.lock();
+            self.evict(&mut instances);
+            instances.get(&key).map(|i| i.definition.clone())
         }
     }
 
@@ -0,0 +1,129 @@
+//! Client-side validation of Block Kit payloads
+//!
+//! Slack rejects malformed blocks with a terse `invalid_blocks` error, which makes it hard to tell
+//! what is wrong with a template. The checks below cover the structure and the most common limits
+//! of the blocks, see https://api.slack.com/reference/block-kit/blocks
+
+use serde_json::Value;
+
+/// The maximum number of blocks in a single message
+const MAX_BLOCKS: usize = 50;
+const MAX_BLOCK_ID_LENGTH: usize = 255;
+
+/// Validates the blocks of a message
+///
+/// Returns a description of the first found problem, pointing at the offending block and field.
+///
+/// # Arguments
+///
+/// * `blocks` - The blocks to validate
+pub fn validate(blocks: &Value) -> Result<(), String> {
+    let blocks = blocks
+        .as_array()
+        .ok_or_else(|| "blocks has to be an array".to_string())?;
+    if blocks.len() > MAX_BLOCKS {
+        return Err(format!(
+            "too many blocks ({}), at most {} are allowed",
+            blocks.len(),
+            MAX_BLOCKS
+        ));
+    }
+    for (i, block) in blocks.iter().enumerate() {
+        validate_block(block).map_err(|e| match block["type"].as_str() {
+            Some(kind) => format!("block {} ({}): {}", i, kind, e),
+            None => format!("block {}: {}", i, e),
+        })?;
+    }
+    Ok(())
+}
+
+fn validate_block(block: &Value) -> Result<(), String> {
+    if !block.is_object() {
+        return Err("has to be an object".into());
+    }
+    let kind = block["type"]
+        .as_str()
+        .ok_or_else(|| "type is missing".to_string())?;
+    if let Some(block_id) = block.get("block_id") {
+        string(block_id, "block_id", MAX_BLOCK_ID_LENGTH)?;
+    }
+    match kind {
+        "section" => {
+            if block.get("text").is_none() && block.get("fields").is_none() {
+                return Err("either text or fields is required".into());
+            }
+            if let Some(text) = block.get("text") {
+                text_object(text, "text", 3000, false)?;
+            }
+            if let Some(fields) = block.get("fields") {
+                for (i, field) in list(fields, "fields", 0, 10)?.iter().enumerate() {
+                    text_object(field, &format!("fields[{}]", i), 2000, false)?;
+                }
+            }
+            Ok(())
+        }
+        "header" => text_object(&block["text"], "text", 150, true),
+        "context" => {
+            for (i, element) in list(&block["elements"], "elements", 1, 10)?
+                .iter()
+                .enumerate()
+            {
+                if element["type"] != "image" {
+                    text_object(element, &format!("elements[{}]", i), 3000, false)?;
+                }
+            }
+            Ok(())
+        }
+        "actions" => list(&block["elements"], "elements", 1, 25).map(|_| ()),
+        "image" => {
+            string(&block["image_url"], "image_url", 3000)?;
+            string(&block["alt_text"], "alt_text", 2000)?;
+            if let Some(title) = block.get("title") {
+                text_object(title, "title", 2000, true)?;
+            }
+            Ok(())
+        }
+        // Slack keeps adding new types of blocks, so the ones not covered above are left for
+        // Slack to validate
+        _ => Ok(()),
+    }
+}
+
+fn text_object(value: &Value, field: &str, max: usize, plain_only: bool) -> Result<(), String> {
+    match value["type"].as_str() {
+        Some("plain_text") => (),
+        Some("mrkdwn") if !plain_only => (),
+        Some(kind) => return Err(format!("{}.type \"{}\" is not allowed", field, kind)),
+        None => return Err(format!("{} has to be a text object", field)),
+    }
+    string(&value["text"], &format!("{}.text", field), max)
+}
+
+fn string(value: &Value, field: &str, max: usize) -> Result<(), String> {
+    let length = value
+        .as_str()
+        .map(|s| s.chars().count())
+        .ok_or_else(|| format!("{} has to be a string", field))?;
+    match length {
+        0 => Err(format!("{} must not be empty", field)),
+        l if l > max => Err(format!(
+            "{} is too long ({} characters), at most {} are allowed",
+            field, l, max
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn list<'a>(value: &'a Value, field: &str, min: usize, max: usize) -> Result<&'a [Value], String> {
+    let items = value
+        .as_array()
+        .ok_or_else(|| format!("{} has to be an array", field))?;
+    match items.len() {
+        l if l < min => Err(format!("{} needs at least {} item(s)", field, min)),
+        l if l > max => Err(format!(
+            "{} has too many items ({}), at most {} are allowed",
+            field, l, max
+        )),
+        _ => Ok(items),
+    }
+}
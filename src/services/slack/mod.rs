@@ -0,0 +1,916 @@
+use super::{CallError, Delivery, FactoryError, Notification, Service, ServiceFactory};
+use crate::k8s::secrets::get_secret;
+use crate::secrets::Secret;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use reqwest::header;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+mod blocks;
+
+const DEFAULT_API_URL: &str = "https://slack.com/api";
+
+fn default_api_url() -> String {
+    DEFAULT_API_URL.into()
+}
+
+#[derive(Deserialize)]
+struct ServiceConfig {
+    /// Name of the secret holding the OAuth token
+    token: String,
+    icon_emoji: Option<String>,
+    #[serde(default = "default_api_url")]
+    api_url: String,
+}
+
+impl ServiceConfig {
+    fn from_value(value: serde_json::Value) -> Result<Self, FactoryError> {
+        serde_json::from_value(value).map_err(|e| FactoryError::ConfigError(e.to_string()))
+    }
+}
+
+#[derive(Deserialize)]
+struct NotificationConfig {
+    channel: String,
+    /// Distinguishes between multiple tracked messages in the same channel
+    thread_key: Option<String>,
+    reaction: Option<String>,
+    #[serde(default)]
+    remove_previous_reaction: bool,
+    /// Unix timestamp to schedule the message at, instead of sending it right away
+    post_at: Option<u64>,
+    /// Number of seconds to delay the message by, instead of sending it right away
+    delay: Option<u64>,
+    /// Whether to cancel the message previously scheduled under the same channel and thread key
+    #[serde(default)]
+    cancel_scheduled: bool,
+    /// Whether to pin (`true`) or unpin (`false`) the primary message
+    pin: Option<bool>,
+    /// Whether to set the channel topic using the "topic" sub-template
+    #[serde(default)]
+    set_topic: bool,
+    /// Whether to send the messages without blocks, instead of failing, when the rendered blocks
+    /// are invalid
+    #[serde(default)]
+    fallback_to_text: bool,
+    /// Removes the tracked messages instead of posting a thread message
+    cleanup: Option<Cleanup>,
+    /// Whether to make the thread message visible to everyone in the channel
+    #[serde(default)]
+    reply_broadcast: bool,
+    #[serde(flatten)]
+    options: MessageOptions,
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Cleanup {
+    /// Update the primary message and delete the thread messages
+    Thread,
+    /// Delete both the primary and the thread messages
+    All,
+}
+
+/// Optional arguments passed along with every posted message
+#[derive(Deserialize, Serialize)]
+struct MessageOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unfurl_links: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unfurl_media: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon_url: Option<String>,
+}
+
+impl NotificationConfig {
+    fn from_value(value: serde_json::Value) -> Result<Self, CallError> {
+        serde_json::from_value(value).map_err(|e| CallError::ConfigError(e.to_string()))
+    }
+
+    /// The Unix timestamp to schedule the message at, if the message should be scheduled
+    fn schedule_at(&self) -> Result<Option<u64>, CallError> {
+        match (self.post_at, self.delay) {
+            (Some(_), Some(_)) => Err(CallError::ConfigError(
+                "post_at and delay are mutually exclusive".into(),
+            )),
+            (Some(post_at), None) => Ok(Some(post_at)),
+            (None, Some(delay)) => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_err(|e| CallError::Fail(e.to_string()))?;
+                Ok(Some(now.as_secs() + delay))
+            }
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+/// Identifies a tracked message by the channel name and an optional thread key
+type ChannelKey = (String, Option<String>);
+
+#[derive(Clone, Deserialize, Serialize)]
+struct Channel {
+    channel_id: String,
+    thread_id: String,
+    /// The reaction most recently added to the primary message by Hermes
+    reaction: Option<String>,
+    /// Timestamps of the messages posted in the thread
+    replies: Vec<String>,
+    /// A link to the primary message
+    #[serde(default)]
+    permalink: Option<String>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+struct ScheduledMessage {
+    channel_id: String,
+    scheduled_message_id: String,
+}
+
+/// The tracked messages, as persisted across restarts
+///
+/// The maps are stored as lists of pairs, as JSON only allows for strings as object keys.
+#[derive(Default, Deserialize, Serialize)]
+struct SlackState {
+    #[serde(default)]
+    channels: Vec<(ChannelKey, Channel)>,
+    #[serde(default)]
+    scheduled: Vec<(ChannelKey, ScheduledMessage)>,
+    #[serde(default)]
+    topics: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct TokenSecret {
+    token: Secret,
+}
+
+/// Shallowly merges the fields of one JSON object into another
+fn merge(target: &mut serde_json::Value, source: serde_json::Value) {
+    if let (Some(target), serde_json::Value::Object(source)) = (target.as_object_mut(), source) {
+        target.extend(source);
+    }
+}
+
+pub struct SlackFactory;
+
+#[async_trait]
+impl ServiceFactory for SlackFactory {
+    async fn from_config(config: serde_json::Value) -> Result<Arc<dyn Service>, FactoryError> {
+        let config = ServiceConfig::from_value(config)?;
+        let token_secret: TokenSecret = get_secret(&config.token)
+            .await
+            .map_err(|e| FactoryError::ConfigError(format!("Invalid token secret: {}", e)))?;
+        Ok(Arc::new(Slack::new(config, token_secret.token)))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SlackMessageResponse {
+    channel: String,
+    ts: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SlackScheduledResponse {
+    channel: String,
+    scheduled_message_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SlackEmptyResponse {}
+
+#[derive(Debug, Deserialize)]
+struct SlackPermalinkResponse {
+    permalink: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SlackErrorResponse {
+    ok: bool,
+    error: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum SlackResponse<T> {
+    // The error variant has to come first, as every successful response would match a less
+    // demanding success variant (i.e. `SlackEmptyResponse`)
+    Error(SlackErrorResponse),
+    Success(T),
+}
+
+#[derive(Debug, Deserialize)]
+struct RenderedTemplate {
+    text: Option<String>,
+    blocks: Option<serde_json::Value>,
+}
+
+pub struct Slack {
+    config: ServiceConfig,
+    token: Secret,
+    channels: Arc<Mutex<HashMap<ChannelKey, Box<Channel>>>>,
+    scheduled: Arc<Mutex<HashMap<ChannelKey, ScheduledMessage>>>,
+    /// The topics most recently set by Hermes, keyed by channel ID
+    topics: Arc<Mutex<HashMap<String, String>>>,
+    /// A client shared by all the calls, so that the connections to Slack can be reused
+    client: reqwest::Client,
+}
+
+impl Slack {
+    fn new(config: ServiceConfig, token: Secret) -> Self {
+        Self {
+            config,
+            token,
+            channels: Arc::new(Mutex::new(HashMap::new())),
+            scheduled: Arc::new(Mutex::new(HashMap::new())),
+            topics: Arc::new(Mutex::new(HashMap::new())),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Instantiates the service from a config holding the OAuth token itself
+    ///
+    /// Unlike `SlackFactory::from_config`, the `token` field is not treated as the name of a secret
+    /// to retrieve the token from. Only meant for the tests, which run outside of Kubernetes, thus
+    /// hidden from the documentation.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - A service config, see `SlackFactory::from_config`
+    #[doc(hidden)]
+    pub fn from_resolved_config(config: serde_json::Value) -> Result<Self, FactoryError> {
+        let mut config = ServiceConfig::from_value(config)?;
+        let token = Secret::new(std::mem::take(&mut config.token));
+        Ok(Self::new(config, token))
+    }
+
+    fn get_channel(&self, key: &ChannelKey) -> Option<Box<Channel>> {
+        self.channels.lock().get(key).cloned()
+    }
+
+    fn update_channel(&self, key: ChannelKey, channel: Channel) {
+        let mut channels = self.channels.lock();
+        channels.insert(key, Box::from(channel));
+    }
+
+    /// Renders a sub-template and validates the resulting blocks
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The notification config
+    /// * `notification` - The notification to render
+    /// * `subtemplate` - Name of the sub-template to render
+    fn render(
+        &self,
+        config: &NotificationConfig,
+        notification: &Notification,
+        subtemplate: &str,
+    ) -> Result<RenderedTemplate, CallError> {
+        let raw_template = notification
+            .render(subtemplate)
+            .map_err(|e| CallError::RenderError(e.to_string()))?;
+        let mut template: RenderedTemplate = serde_json::from_str(raw_template.as_str())
+            .map_err(|e| CallError::RenderError(e.to_string()))?;
+        if let Some(Err(e)) = template.blocks.as_ref().map(blocks::validate) {
+            if !config.fallback_to_text || template.text.is_none() {
+                return Err(CallError::RenderError(format!(
+                    "Invalid blocks in the \"{}\" sub-template: {}",
+                    subtemplate, e
+                )));
+            }
+            template.blocks = None;
+        }
+        Ok(template)
+    }
+
+    /// Renders a sub-template that the template is allowed to omit
+    fn render_optional(
+        &self,
+        config: &NotificationConfig,
+        notification: &Notification,
+        subtemplate: &str,
+    ) -> Result<Option<RenderedTemplate>, CallError> {
+        if notification.template.contains_key(subtemplate) {
+            self.render(config, notification, subtemplate).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Builds a message payload out of a rendered template
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Notification specific message options
+    /// * `template` - The rendered template holding the message content
+    /// * `fields` - Call specific fields, i.e. the channel and timestamp of the message
+    fn payload(
+        &self,
+        options: &MessageOptions,
+        template: RenderedTemplate,
+        fields: serde_json::Value,
+    ) -> serde_json::Value {
+        let mut payload = serde_json::json!({
+            // Slack prefers the emoji over the image, so the latter has to take precedence here
+            "icon_emoji": self.config.icon_emoji.as_ref().filter(|_| options.icon_url.is_none()),
+            "text": template.text,
+            "blocks": template.blocks,
+        });
+        merge(&mut payload, serde_json::json!(options));
+        merge(&mut payload, fields);
+        payload
+    }
+
+    async fn post<T>(&self, call: &str, payload: &serde_json::Value) -> Result<T, String>
+    where
+        T: DeserializeOwned,
+    {
+        let url = format!("{}/{}", self.config.api_url.trim_end_matches('/'), call);
+        self.send(self.client.post(url).json(&payload)).await
+    }
+
+    /// Calls a read method of the Slack API, which, unlike the write methods, does not accept JSON
+    async fn get<T>(&self, call: &str, query: &[(&str, &str)]) -> Result<T, String>
+    where
+        T: DeserializeOwned,
+    {
+        let url = format!("{}/{}", self.config.api_url.trim_end_matches('/'), call);
+        self.send(self.client.get(url).query(query)).await
+    }
+
+    async fn send<T>(&self, request: reqwest::RequestBuilder) -> Result<T, String>
+    where
+        T: DeserializeOwned,
+    {
+        let response: SlackResponse<T> = request
+            .header(
+                header::AUTHORIZATION,
+                format!("Bearer {}", self.token.expose()),
+            )
+            .send()
+            .await
+            .map_err(|e| format!("Unexpected error: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Slack response parsing error: {}", e))?;
+
+        match response {
+            SlackResponse::Success(r) => Ok(r),
+            SlackResponse::Error(r) if !r.ok => Err(r.error),
+            SlackResponse::Error(r) => Err(format!("Unexpected Slack response: {}", r.error)),
+        }
+    }
+
+    /// Schedules a message to be posted in the future
+    ///
+    /// A message previously scheduled under the same key is replaced by the new one.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to track the scheduled message under
+    /// * `config` - The notification config
+    /// * `template` - The rendered template holding the message content
+    /// * `post_at` - Unix timestamp to post the message at
+    async fn schedule(
+        &self,
+        key: ChannelKey,
+        config: &NotificationConfig,
+        template: RenderedTemplate,
+        post_at: u64,
+    ) -> Result<String, CallError> {
+        self.cancel_scheduled(&key).await?;
+        let payload = self.payload(
+            &config.options,
+            template,
+            serde_json::json!({
+                "channel": config.channel,
+                "post_at": post_at,
+            }),
+        );
+        let SlackScheduledResponse {
+            channel,
+            scheduled_message_id,
+        } = self
+            .post("chat.scheduleMessage", &payload)
+            .await
+            .map_err(CallError::Fail)?;
+        self.scheduled.lock().insert(
+            key,
+            ScheduledMessage {
+                channel_id: channel,
+                scheduled_message_id: scheduled_message_id.clone(),
+            },
+        );
+        Ok(scheduled_message_id)
+    }
+
+    /// Cancels the message scheduled under the given key, if any
+    ///
+    /// Returns whether there was a pending message to cancel.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key the scheduled message is tracked under
+    async fn cancel_scheduled(&self, key: &ChannelKey) -> Result<bool, CallError> {
+        let scheduled = match self.scheduled.lock().remove(key) {
+            Some(scheduled) => scheduled,
+            None => return Ok(false),
+        };
+        let payload = serde_json::json!({
+            "channel": scheduled.channel_id,
+            "scheduled_message_id": scheduled.scheduled_message_id,
+        });
+        match self
+            .post::<SlackEmptyResponse>("chat.deleteScheduledMessage", &payload)
+            .await
+        {
+            // The message has most likely already been posted
+            Err(e) if e == "invalid_scheduled_message_id" => Ok(false),
+            r => r.map(|_| true).map_err(CallError::Fail),
+        }
+    }
+
+    /// Deletes all the tracked messages and stops tracking them
+    ///
+    /// Returns the number of deleted messages.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key the messages are tracked under
+    async fn delete_channel(&self, key: ChannelKey) -> Result<usize, CallError> {
+        let channel = match self.channels.lock().remove(&key) {
+            Some(channel) => channel,
+            None => return Ok(0),
+        };
+        let mut messages = channel.replies.clone();
+        messages.push(channel.thread_id.clone());
+        if let Err(e) = self.delete(&channel.channel_id, &messages).await {
+            // Keep tracking the messages, so that the cleanup can be retried
+            self.update_channel(key, *channel);
+            return Err(e);
+        }
+        Ok(messages.len())
+    }
+
+    /// Creates new or updates the existing primary notification
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The notification config
+    /// * `template` - The rendered primary template
+    /// * `channel` - Name or ID of the channel to post the message in
+    /// * `ts` - Timestamp of the message to update, if it already exists
+    async fn post_primary(
+        &self,
+        config: &NotificationConfig,
+        template: RenderedTemplate,
+        channel: &str,
+        ts: Option<&str>,
+    ) -> Result<SlackMessageResponse, CallError> {
+        let payload = self.payload(
+            &config.options,
+            template,
+            serde_json::json!({
+                "channel": channel,
+                "ts": ts,
+            }),
+        );
+        let call = ts.and(Some("chat.update")).unwrap_or("chat.postMessage");
+        self.post(call, &payload).await.map_err(CallError::Fail)
+    }
+
+    /// Retrieves a link to a message, unless it is already known
+    ///
+    /// The link is only a convenience, so failing to retrieve it does not fail the notification.
+    /// It is retried with the next notification instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - ID of the channel the message lives in
+    /// * `ts` - Timestamp of the message
+    /// * `permalink` - The previously retrieved link, if any
+    async fn permalink(
+        &self,
+        channel: &str,
+        ts: &str,
+        permalink: Option<String>,
+    ) -> Option<String> {
+        if permalink.is_some() {
+            return permalink;
+        }
+        self.get(
+            "chat.getPermalink",
+            &[("channel", channel), ("message_ts", ts)],
+        )
+        .await
+        .map(|r: SlackPermalinkResponse| r.permalink)
+        .ok()
+    }
+
+    /// Creates new secondary notification (a thread message), if the template provides one
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The notification config
+    /// * `template` - The rendered secondary template, if any
+    /// * `channel` - ID of the channel the primary message lives in
+    /// * `thread_ts` - Timestamp of the primary message
+    async fn post_secondary(
+        &self,
+        config: &NotificationConfig,
+        template: Option<RenderedTemplate>,
+        channel: &str,
+        thread_ts: &str,
+    ) -> Result<Option<String>, CallError> {
+        let template = match template {
+            Some(template) => template,
+            None => return Ok(None),
+        };
+        let payload = self.payload(
+            &config.options,
+            template,
+            serde_json::json!({
+                "channel": channel,
+                "thread_ts": thread_ts,
+                "reply_broadcast": config.reply_broadcast,
+            }),
+        );
+        self.post::<SlackMessageResponse>("chat.postMessage", &payload)
+            .await
+            .map(|r| Some(r.ts))
+            .map_err(CallError::Fail)
+    }
+
+    /// Deletes the given messages, skipping the ones that are already gone
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - ID of the channel the messages live in
+    /// * `messages` - Timestamps of the messages to delete
+    async fn delete(&self, channel: &str, messages: &[String]) -> Result<(), CallError> {
+        // The deletions are issued one by one, as the method is rather strictly rate limited
+        for ts in messages {
+            let payload = serde_json::json!({
+                "channel": channel,
+                "ts": ts,
+            });
+            match self
+                .post::<SlackEmptyResponse>("chat.delete", &payload)
+                .await
+            {
+                Err(e) if e == "message_not_found" => (),
+                r => r.map(|_| ()).map_err(CallError::Fail)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the reaction requested by the notification config, if any
+    ///
+    /// Returns the reaction that the primary message is left with.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The notification config
+    /// * `channel` - ID of the channel the primary message lives in
+    /// * `ts` - Timestamp of the primary message
+    /// * `current` - The reaction previously added by Hermes, if any
+    async fn update_reaction(
+        &self,
+        config: &NotificationConfig,
+        channel: &str,
+        ts: &str,
+        current: Option<String>,
+    ) -> Result<Option<String>, CallError> {
+        let reaction = match &config.reaction {
+            Some(reaction) => reaction,
+            None => return Ok(current),
+        };
+        let previous = current
+            .as_deref()
+            .filter(|_| config.remove_previous_reaction);
+        self.react(channel, ts, reaction, previous)
+            .await
+            .map_err(CallError::Fail)?;
+        Ok(Some(reaction.trim_matches(':').into()))
+    }
+
+    /// Pins or unpins the primary message, as requested by the notification config
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The notification config
+    /// * `channel` - ID of the channel the primary message lives in
+    /// * `ts` - Timestamp of the primary message
+    async fn update_pin(
+        &self,
+        config: &NotificationConfig,
+        channel: &str,
+        ts: &str,
+    ) -> Result<(), CallError> {
+        let (call, noop_error) = match config.pin {
+            Some(true) => ("pins.add", "already_pinned"),
+            Some(false) => ("pins.remove", "no_pin"),
+            None => return Ok(()),
+        };
+        let payload = serde_json::json!({
+            "channel": channel,
+            "timestamp": ts,
+        });
+        match self.post::<SlackEmptyResponse>(call, &payload).await {
+            Err(e) if e == noop_error => Ok(()),
+            r => r.map(|_| ()).map_err(CallError::Fail),
+        }
+    }
+
+    /// Sets the channel topic, unless it is already set to the given one
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - ID of the channel to set the topic of
+    /// * `topic` - The rendered topic, if the topic should be set
+    async fn update_topic(&self, channel: &str, topic: Option<String>) -> Result<(), CallError> {
+        let topic = match topic {
+            // Every topic change is announced in the channel, so avoid the unnecessary ones
+            Some(topic) if self.topics.lock().get(channel) != Some(&topic) => topic,
+            _ => return Ok(()),
+        };
+        let payload = serde_json::json!({
+            "channel": channel,
+            "topic": topic,
+        });
+        self.post::<SlackEmptyResponse>("conversations.setTopic", &payload)
+            .await
+            .map_err(CallError::Fail)?;
+        self.topics.lock().insert(channel.into(), topic);
+        Ok(())
+    }
+
+    /// Reflects the state of the workflow as a reaction on the primary message
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - ID of the channel the primary message lives in
+    /// * `ts` - Timestamp of the primary message
+    /// * `reaction` - Name of the emoji to add, with or without the surrounding colons
+    /// * `previous` - The reaction to remove before adding the new one, if any
+    async fn react(
+        &self,
+        channel: &str,
+        ts: &str,
+        reaction: &str,
+        previous: Option<&str>,
+    ) -> Result<(), String> {
+        let reaction = reaction.trim_matches(':');
+        if let Some(previous) = previous.filter(|&p| p != reaction) {
+            let payload = serde_json::json!({
+                "channel": channel,
+                "timestamp": ts,
+                "name": previous,
+            });
+            match self
+                .post::<SlackEmptyResponse>("reactions.remove", &payload)
+                .await
+            {
+                // Someone might have already removed the reaction manually
+                Err(e) if e == "no_reaction" => (),
+                r => r.map(|_| ())?,
+            }
+        }
+        let payload = serde_json::json!({
+            "channel": channel,
+            "timestamp": ts,
+            "name": reaction,
+        });
+        match self
+            .post::<SlackEmptyResponse>("reactions.add", &payload)
+            .await
+        {
+            Err(e) if e == "already_reacted" => Ok(()),
+            r => r.map(|_| ()),
+        }
+    }
+}
+
+#[async_trait]
+impl Service for Slack {
+    async fn notify(
+        &self,
+        config: serde_json::Value,
+        notification: Notification,
+    ) -> Result<Delivery, CallError> {
+        let notification_config = NotificationConfig::from_value(config)?;
+        let key = (
+            notification_config.channel.clone(),
+            notification_config.thread_key.clone(),
+        );
+
+        // Scheduled messages are not tracked as the primary notification, as they do not exist
+        // until they are posted
+        if notification_config.cancel_scheduled {
+            let message = if self.cancel_scheduled(&key).await? {
+                "Scheduled notification cancelled"
+            } else {
+                "No scheduled notification to cancel"
+            };
+            return Ok(Delivery::with_message(message));
+        }
+        if let Some(post_at) = notification_config.schedule_at()? {
+            let template = self.render(&notification_config, &notification, "primary")?;
+            let id = self
+                .schedule(key, &notification_config, template, post_at)
+                .await?;
+            return Ok(
+                Delivery::with_message(format!("Notification scheduled: {}", id))
+                    .output("scheduled_message_id", id),
+            );
+        }
+
+        if notification_config.cleanup == Some(Cleanup::All) {
+            let deleted = self.delete_channel(key).await?;
+            return Ok(Delivery::with_message(format!(
+                "Deleted {} message(s)",
+                deleted
+            )));
+        }
+
+        // Render everything upfront, so that a broken template does not leave a half-sent
+        // notification behind
+        let primary = self.render(&notification_config, &notification, "primary")?;
+        let secondary = match notification_config.cleanup {
+            Some(Cleanup::Thread) => None,
+            _ => self.render_optional(&notification_config, &notification, "secondary")?,
+        };
+        let topic = if notification_config.set_topic {
+            let topic = notification
+                .render("topic")
+                .map_err(|e| CallError::RenderError(format!("Topic: {}", e)))?;
+            Some(topic.trim().into())
+        } else {
+            None
+        };
+
+        // Retrieve the cached data about the channel, if any
+        let channel = match self.get_channel(&key) {
+            Some(channel) => {
+                // The channel ID and thread ID are known, so the primary and secondary
+                // notifications can be issued in parallel
+                let Channel {
+                    channel_id,
+                    thread_id,
+                    reaction,
+                    replies,
+                    permalink,
+                } = *channel;
+                let (updated, new_reaction, reply, pinned, topic_set) = tokio::join!(
+                    self.post_primary(&notification_config, primary, &channel_id, Some(&thread_id)),
+                    self.update_reaction(
+                        &notification_config,
+                        &channel_id,
+                        &thread_id,
+                        reaction.clone()
+                    ),
+                    self.post_secondary(&notification_config, secondary, &channel_id, &thread_id),
+                    self.update_pin(&notification_config, &channel_id, &thread_id),
+                    self.update_topic(&channel_id, topic),
+                );
+                // Keep track of what has been sent before reporting any failure, so that the reply
+                // can still be cleaned up and the reaction replaced later on
+                let mut channel = Channel {
+                    channel_id,
+                    thread_id,
+                    reaction: new_reaction.as_ref().map_or(reaction, Clone::clone),
+                    replies: replies.clone(),
+                    permalink,
+                };
+                channel
+                    .replies
+                    .extend(reply.as_ref().ok().cloned().flatten());
+                self.update_channel(key.clone(), channel.clone());
+                updated?;
+                new_reaction?;
+                reply?;
+                pinned?;
+                topic_set?;
+
+                channel.permalink = self
+                    .permalink(&channel.channel_id, &channel.thread_id, channel.permalink)
+                    .await;
+                if notification_config.cleanup == Some(Cleanup::Thread) {
+                    self.delete(&channel.channel_id, &replies).await?;
+                    channel.replies.clear();
+                }
+                channel
+            }
+            None => {
+                // Create the primary notification first, as the thread can only be posted to
+                // once its timestamp is known
+                let SlackMessageResponse {
+                    channel: channel_id,
+                    ts: thread_id,
+                } = self
+                    .post_primary(
+                        &notification_config,
+                        primary,
+                        &notification_config.channel,
+                        None,
+                    )
+                    .await?;
+                // Keep track of the message right away, so that a failure of any of the following
+                // calls does not result in another primary message being posted next time
+                let mut channel = Channel {
+                    channel_id,
+                    thread_id,
+                    reaction: None,
+                    replies: vec![],
+                    permalink: None,
+                };
+                self.update_channel(key.clone(), channel.clone());
+                let (reaction, reply, pinned, topic_set) = tokio::join!(
+                    self.update_reaction(
+                        &notification_config,
+                        &channel.channel_id,
+                        &channel.thread_id,
+                        None
+                    ),
+                    self.post_secondary(
+                        &notification_config,
+                        secondary,
+                        &channel.channel_id,
+                        &channel.thread_id
+                    ),
+                    self.update_pin(
+                        &notification_config,
+                        &channel.channel_id,
+                        &channel.thread_id
+                    ),
+                    self.update_topic(&channel.channel_id, topic),
+                );
+                // Keep track of what has been sent before reporting any failure, so that the reply
+                // can still be cleaned up and the reaction replaced later on
+                channel.reaction = reaction.as_ref().ok().cloned().flatten();
+                channel.replies = reply.as_ref().ok().cloned().flatten().into_iter().collect();
+                self.update_channel(key.clone(), channel.clone());
+                reaction?;
+                reply?;
+                pinned?;
+                topic_set?;
+
+                channel.permalink = self
+                    .permalink(&channel.channel_id, &channel.thread_id, None)
+                    .await;
+                channel
+            }
+        };
+
+        let delivery = Delivery::default()
+            .output("channel", &channel.channel_id)
+            .output("ts", &channel.thread_id)
+            .output("permalink", channel.permalink.clone().unwrap_or_default());
+
+        // Update the cache
+        self.update_channel(key, channel);
+
+        Ok(delivery)
+    }
+
+    fn state(&self) -> serde_json::Value {
+        let state = SlackState {
+            channels: self
+                .channels
+                .lock()
+                .iter()
+                .map(|(k, c)| (k.clone(), *c.clone()))
+                .collect(),
+            scheduled: self
+                .scheduled
+                .lock()
+                .iter()
+                .map(|(k, m)| (k.clone(), m.clone()))
+                .collect(),
+            topics: self.topics.lock().clone(),
+        };
+        serde_json::to_value(state).unwrap_or_default()
+    }
+
+    fn restore(&self, state: serde_json::Value) -> Result<(), CallError> {
+        if state.is_null() {
+            return Ok(());
+        }
+        let state: SlackState = serde_json::from_value(state)
+            .map_err(|e| CallError::Fail(format!("Invalid Slack state: {}", e)))?;
+        self.channels
+            .lock()
+            .extend(state.channels.into_iter().map(|(k, c)| (k, Box::new(c))));
+        self.scheduled.lock().extend(state.scheduled);
+        self.topics.lock().extend(state.topics);
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long the service instance of a workflow and its state are kept in memory after their last
+/// use
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+pub enum StateError {
+    /// Any error that might happen when reading or writing the state, i.e. failing to update a
+    /// ConfigMap in Kubernetes
+    GenericError(String),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateError::GenericError(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+/// Identifies the workflow that a state belongs to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Scope {
+    /// UID of the workflow
+    pub uid: String,
+    /// Name of the workflow, if known
+    pub name: Option<String>,
+}
+
+impl Scope {
+    /// The scope used when the request does not carry any workflow metadata
+    pub fn global() -> Self {
+        Self {
+            uid: "global".into(),
+            name: None,
+        }
+    }
+}
+
+/// The persisted state of a service instance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceState {
+    /// Name of the service, i.e. slack
+    pub service: String,
+    /// The config the service instance was set up with
+    pub config: serde_json::Value,
+    /// Service specific data, i.e. IDs of the sent messages
+    #[serde(default)]
+    pub data: serde_json::Value,
+}
+
+/// Persists the state of the service instances, so that it can survive a restart of Hermes
+#[async_trait]
+pub trait StateStore: Sync + Send {
+    /// Retrieves the state of a service instance
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - The workflow the service instance belongs to
+    /// * `alias` - The alias of the service instance
+    async fn get(&self, scope: &Scope, alias: &str) -> Result<Option<ServiceState>, StateError>;
+
+    /// Stores the state of a service instance
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - The workflow the service instance belongs to
+    /// * `alias` - The alias of the service instance
+    /// * `state` - The state to store
+    async fn put(&self, scope: &Scope, alias: &str, state: ServiceState) -> Result<(), StateError>;
+}
+
+pub type StateStoreRef = Arc<dyn StateStore>;
+
+pub mod stores {
+    use super::{Scope, ServiceState, StateError, StateStore, IDLE_TIMEOUT};
+    use async_trait::async_trait;
+    use parking_lot::Mutex;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    struct StoredState {
+        state: ServiceState,
+        used_at: Instant,
+    }
+
+    /// A state store that keeps the state in memory, i.e. only for the lifetime of the process
+    pub struct MemoryStateStore {
+        states: Mutex<HashMap<(Scope, String), StoredState>>,
+        idle_timeout: Duration,
+    }
+
+    impl MemoryStateStore {
+        pub fn new() -> Arc<Self> {
+            Self::with_idle_timeout(IDLE_TIMEOUT)
+        }
+
+        /// Creates a store dropping the states of the workflows after they have not been used for
+        /// the given time
+        ///
+        /// The states of the instances set up globally are never dropped.
+        ///
+        /// # Arguments
+        ///
+        /// * `idle_timeout` - How long a state is kept after its last use
+        pub fn with_idle_timeout(idle_timeout: Duration) -> Arc<Self> {
+            Arc::new(Self {
+                states: Mutex::new(HashMap::new()),
+                idle_timeout,
+            })
+        }
+
+        fn evict(&self, states: &mut HashMap<(Scope, String), StoredState>) {
+            let global = Scope::global().uid;
+            states.retain(|(scope, _), s| {
+                scope.uid == global || s.used_at.elapsed() < self.idle_timeout
+            });
+        }
+    }
+
+    #[async_trait]
+    impl StateStore for MemoryStateStore {
+        async fn get(
+            &self,
+            scope: &Scope,
+            alias: &str,
+        ) -> Result<Option<ServiceState>, StateError> {
+            let key = (scope.clone(), alias.to_string());
+            let mut states = self.states.lock();
+            self.evict(&mut states);
+            Ok(states.get_mut(&key).map(|s| {
+                s.used_at = Instant::now();
+                s.state.clone()
+            }))
+        }
+
+        async fn put(
+            &self,
+            scope: &Scope,
+            alias: &str,
+            state: ServiceState,
+        ) -> Result<(), StateError> {
+            let key = (scope.clone(), alias.to_string());
+            let mut states = self.states.lock();
+            self.evict(&mut states);
+            states.insert(
+                key,
+                StoredState {
+                    state,
+                    used_at: Instant::now(),
+                },
+            );
+            Ok(())
+        }
+    }
+}
@@ -16,7 +16,7 @@ pub enum TemplateError {
 
 impl fmt::Display for TemplateError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match &*self {
+        match self {
             TemplateError::NotFound => write!(f, "Template not found"),
             TemplateError::InvalidFormat(s) => write!(f, "Invalid template format: {}", s),
             TemplateError::GenericError(s) => write!(f, "{}", s),
@@ -28,7 +28,7 @@ impl fmt::Display for TemplateError {
 ///
 /// A simple template can consist of multiple sub-templates. This allows for the services to have
 /// more flexibility in how to handle the notifications. For example, the provided Slack service
-/// is expecting a "primary" and a "secondary" template, where the primary one is used to
+/// is expecting a "primary" and an optional "secondary" template, where the primary one is used to
 /// create/update a detailed channel message and the "secondary" to provide a less detailed message
 /// in the thread under the channel message.
 pub type Template = HashMap<String, String>;
@@ -0,0 +1,146 @@
+use crate::period::Period;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Limits how many notifications a service instance sends
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Throttle {
+    /// How many notifications can be sent in a burst
+    pub limit: u32,
+    /// How long it takes to recover the whole limit
+    pub per: Period,
+}
+
+/// Throttles keyed by the aliases of the service instances they apply to
+pub type Throttles = HashMap<String, Throttle>;
+
+/// A notification that duplicates are suppressed for
+struct Sent {
+    sent_at: Instant,
+    window: Duration,
+    /// How many duplicates have been suppressed so far
+    suppressed: usize,
+}
+
+/// A token bucket, refilled continuously up to its limit
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    per: Duration,
+}
+
+/// Suppresses the duplicate notifications and throttles the notifications sent by a service
+/// instance
+///
+/// The state is kept in memory only, i.e. it does not survive a restart of Hermes.
+#[derive(Default)]
+pub struct Limiter {
+    throttles: Arc<Throttles>,
+    sent: Mutex<HashMap<String, Sent>>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+pub type LimiterRef = Arc<Limiter>;
+
+impl Limiter {
+    pub fn new() -> LimiterRef {
+        Arc::new(Self::default())
+    }
+
+    /// Creates a limiter throttling the given service instances
+    ///
+    /// # Arguments
+    ///
+    /// * `throttles` - The throttles, keyed by the aliases of the service instances
+    pub fn with_throttles(throttles: Arc<Throttles>) -> LimiterRef {
+        Arc::new(Self {
+            throttles,
+            ..Default::default()
+        })
+    }
+
+    /// Registers a notification, unless its duplicate has been registered within the window
+    ///
+    /// Returns the number of the duplicates suppressed so far when the notification is a
+    /// duplicate.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key identifying the duplicates
+    /// * `window` - How long the duplicates are suppressed for
+    pub fn deduplicate(&self, key: &str, window: Duration) -> Option<usize> {
+        self.prune();
+        let mut sent = self.sent.lock();
+        if let Some(s) = sent.get_mut(key) {
+            s.suppressed += 1;
+            return Some(s.suppressed);
+        }
+        sent.insert(
+            key.into(),
+            Sent {
+                sent_at: Instant::now(),
+                window,
+                suppressed: 0,
+            },
+        );
+        None
+    }
+
+    /// Forgets a registered notification, i.e. because it has not been sent after all
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key identifying the duplicates
+    pub fn forget(&self, key: &str) {
+        self.sent.lock().remove(key);
+    }
+
+    /// Takes a token from the bucket of the given service instance, if it is throttled
+    ///
+    /// Returns the exceeded throttle when there are no tokens left. The bucket is shared by all
+    /// the workflows notifying the service instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `alias` - The alias of the service instance
+    pub fn throttle(&self, alias: &str) -> Result<(), Throttle> {
+        let throttle = match self.throttles.get(alias) {
+            Some(throttle) => *throttle,
+            None => return Ok(()),
+        };
+        let (limit, per) = (throttle.limit, throttle.per.0);
+        self.prune();
+        let mut buckets = self.buckets.lock();
+        let now = Instant::now();
+        let bucket = buckets.entry(alias.into()).or_insert(Bucket {
+            tokens: limit.into(),
+            updated_at: now,
+            per,
+        });
+        let refilled = now.duration_since(bucket.updated_at).as_secs_f64() / per.as_secs_f64();
+        bucket.tokens = (bucket.tokens + refilled * f64::from(limit)).min(limit.into());
+        bucket.updated_at = now;
+        bucket.per = per;
+        if bucket.tokens < 1.0 {
+            return Err(throttle);
+        }
+        bucket.tokens -= 1.0;
+        Ok(())
+    }
+
+    /// Drops the notifications whose window has passed and the buckets that have refilled, so
+    /// that the keys of finished workflows do not pile up
+    fn prune(&self) {
+        self.sent
+            .lock()
+            .retain(|_, s| s.sent_at.elapsed() < s.window);
+        // A bucket that has had the time to refill is as good as a new one
+        self.buckets
+            .lock()
+            .retain(|_, b| b.updated_at.elapsed() < b.per);
+    }
+}
@@ -0,0 +1,246 @@
+use chrono_tz::Tz;
+use k8s_openapi::chrono::{
+    DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone,
+    Timelike, Utc, Weekday,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// The hours the notifications can be delivered in, e.g. `08:00-18:00`
+///
+/// The hours wrap around midnight when the end precedes the start, e.g. `22:00-06:00`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl FromStr for Hours {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid hours \"{}\", expected e.g. 08:00-18:00", s);
+        let (start, end) = s.split_once('-').ok_or_else(invalid)?;
+        let parse = |t: &str| NaiveTime::parse_from_str(t.trim(), "%H:%M").map_err(|_| invalid());
+        Ok(Hours {
+            start: parse(start)?,
+            end: parse(end)?,
+        })
+    }
+}
+
+impl fmt::Display for Hours {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+/// The timezone of a delivery window, either a fixed offset from UTC, e.g. `UTC`, `+02:00` or
+/// `-05:30`, or a name from the IANA database, e.g. `Europe/Stockholm`
+///
+/// Only the named timezones follow the daylight saving time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timezone {
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl Default for Timezone {
+    fn default() -> Self {
+        Timezone::Fixed(FixedOffset::east_opt(0).expect("UTC is a valid offset"))
+    }
+}
+
+impl Timezone {
+    /// Converts the given time to the local time of the timezone
+    ///
+    /// # Arguments
+    ///
+    /// * `at` - The time to convert
+    fn local(&self, at: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Timezone::Fixed(offset) => at.with_timezone(offset).naive_local(),
+            Timezone::Named(tz) => at.with_timezone(tz).naive_local(),
+        }
+    }
+
+    /// Converts the given local time of the timezone to UTC
+    ///
+    /// An ambiguous local time, i.e. repeated when the clocks go back, resolves to its earlier
+    /// occurrence, while a local time skipped when the clocks go forward moves past the gap.
+    ///
+    /// # Arguments
+    ///
+    /// * `local` - The local time to convert
+    fn utc(&self, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+        let resolve = |local: &NaiveDateTime| match self {
+            Timezone::Fixed(offset) => offset
+                .from_local_datetime(local)
+                .earliest()
+                .map(|t| t.with_timezone(&Utc)),
+            Timezone::Named(tz) => tz
+                .from_local_datetime(local)
+                .earliest()
+                .map(|t| t.with_timezone(&Utc)),
+        };
+        resolve(&local).or_else(|| resolve(&(local + Duration::hours(1))))
+    }
+}
+
+impl FromStr for Timezone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid timezone \"{}\", expected UTC, e.g. +02:00 or e.g. Europe/Stockholm",
+                s
+            )
+        };
+        if matches!(s, "UTC" | "Z") {
+            return Ok(Timezone::default());
+        }
+        let sign = match s.chars().next() {
+            Some('+') => 1,
+            Some('-') => -1,
+            _ => return s.parse().map(Timezone::Named).map_err(|_| invalid()),
+        };
+        let hours = NaiveTime::parse_from_str(&s[1..], "%H:%M").map_err(|_| invalid())?;
+        FixedOffset::east_opt(sign * hours.num_seconds_from_midnight() as i32)
+            .map(Timezone::Fixed)
+            .ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for Timezone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Timezone::Fixed(offset) => write!(f, "{}", offset),
+            Timezone::Named(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
+
+/// Implements the (de)serialization of the types parsed from a string
+macro_rules! string_serde {
+    ($type:ty) => {
+        impl Serialize for $type {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $type {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                String::deserialize(deserializer)?
+                    .parse()
+                    .map_err(de::Error::custom)
+            }
+        }
+    };
+}
+
+string_serde!(Hours);
+string_serde!(Timezone);
+
+/// A service instance to deliver the notifications to outside the delivery window
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Fallback {
+    /// The alias of the service instance
+    pub target: String,
+    /// Service specific config, replacing the one of the original target
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<serde_json::Value>,
+}
+
+/// What happens to the notifications sent outside the delivery window
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outside {
+    /// The notification is not delivered at all
+    #[default]
+    Drop,
+    /// The notification is delivered once the window opens
+    Delay,
+    /// The notification is delivered to a different service instance
+    Downgrade(Fallback),
+}
+
+fn every_day() -> Vec<Weekday> {
+    vec![
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+        Weekday::Sat,
+        Weekday::Sun,
+    ]
+}
+
+/// The time the notifications can be delivered in
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeliveryWindow {
+    /// The timezone of the window, UTC by default
+    #[serde(default)]
+    pub timezone: Timezone,
+    /// The days the window opens on, every day by default
+    #[serde(default = "every_day")]
+    pub days: Vec<Weekday>,
+    /// The hours the window is open in
+    pub hours: Hours,
+    /// What happens to the notifications sent outside the window
+    #[serde(default)]
+    pub outside: Outside,
+}
+
+/// Delivery windows keyed by the aliases of the service instances they apply to
+pub type DeliveryWindows = HashMap<String, DeliveryWindow>;
+
+impl DeliveryWindow {
+    /// Checks whether the window is open at the given time
+    ///
+    /// # Arguments
+    ///
+    /// * `at` - The time to check
+    pub fn is_open(&self, at: DateTime<Utc>) -> bool {
+        let local = self.timezone.local(at);
+        let (date, time) = (local.date(), local.time());
+        let Hours { start, end } = self.hours;
+        if start < end {
+            self.opens_on(date) && start <= time && time < end
+        } else {
+            // Either a whole day, or the hours wrap around midnight
+            let yesterday = date.pred_opt();
+            (self.opens_on(date) && time >= start)
+                || (yesterday.is_some_and(|d| self.opens_on(d)) && time < end)
+        }
+    }
+
+    /// Returns the next time the window opens at, after the given time
+    ///
+    /// # Arguments
+    ///
+    /// * `at` - The time to start looking from
+    pub fn next_opening(&self, at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let date = self.timezone.local(at).date();
+        (0..=7)
+            .map(|days| date + Duration::days(days))
+            .filter(|date| self.opens_on(*date))
+            .filter_map(|date| self.timezone.utc(date.and_time(self.hours.start)))
+            .find(|opening| *opening > at)
+    }
+
+    fn opens_on(&self, date: NaiveDate) -> bool {
+        self.days.contains(&date.weekday())
+    }
+}
@@ -0,0 +1,43 @@
+use async_trait::async_trait;
+use std::fmt;
+use std::sync::Arc;
+
+pub enum WorkflowError {
+    /// The workflow was not found
+    NotFound,
+    /// The workflow is not in a state that allows for the requested operation, i.e. resuming a
+    /// workflow that is not suspended
+    InvalidState(String),
+    /// Any other error that might happen when talking to the Kubernetes API
+    GenericError(String),
+}
+
+impl fmt::Display for WorkflowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WorkflowError::NotFound => write!(f, "Workflow not found"),
+            WorkflowError::InvalidState(s) => write!(f, "Invalid workflow state: {}", s),
+            WorkflowError::GenericError(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+/// Provides a way to control running workflows
+#[async_trait]
+pub trait WorkflowController: Sync + Send {
+    /// Resumes a suspended workflow
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the workflow to resume
+    async fn resume(&self, name: &str) -> Result<(), WorkflowError>;
+
+    /// Stops a workflow, letting its exit handlers run
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the workflow to stop
+    async fn stop(&self, name: &str) -> Result<(), WorkflowError>;
+}
+
+pub type WorkflowControllerRef = Arc<dyn WorkflowController>;
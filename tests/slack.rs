@@ -0,0 +1,911 @@
+use argo_hermes::services::slack::Slack;
+use argo_hermes::services::{CallError, Notification, Service};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+mod mocks {
+    use parking_lot::Mutex;
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use warp::Filter;
+
+    #[derive(Debug, Clone)]
+    pub struct ApiCall {
+        pub method: String,
+        pub authorization: Option<String>,
+        pub payload: serde_json::Value,
+    }
+
+    /// A fake Slack API recording every call made to it
+    ///
+    /// Messages posted to the "missing" channel fail with `channel_not_found`, mimicking the
+    /// behavior of the real API, while the `failing` methods fail with `missing_scope`. The calls
+    /// to the read methods are recorded separately, as they do not have any effect on the
+    /// messages.
+    #[derive(Clone, Default)]
+    pub struct SlackApi {
+        pub calls: Arc<Mutex<Vec<ApiCall>>>,
+        pub reads: Arc<Mutex<Vec<ApiCall>>>,
+        pub failing: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    impl SlackApi {
+        /// Starts the fake API on an ephemeral port and returns its base URL
+        pub fn start(&self) -> String {
+            let api = self.clone();
+            let write = warp::path!("api" / String)
+                .and(warp::post())
+                .and(warp::header::optional::<String>("authorization"))
+                .and(warp::body::json())
+                .map(move |method, authorization, payload| {
+                    warp::reply::json(&api.call(ApiCall {
+                        method,
+                        authorization,
+                        payload,
+                    }))
+                });
+            // The read methods take their arguments as query parameters
+            let api = self.clone();
+            let read = warp::path!("api" / String)
+                .and(warp::get())
+                .and(warp::header::optional::<String>("authorization"))
+                .and(warp::query::<HashMap<String, String>>())
+                .map(move |method, authorization, query| {
+                    warp::reply::json(&api.read(ApiCall {
+                        method,
+                        authorization,
+                        payload: serde_json::json!(query),
+                    }))
+                });
+            let routes = write.or(read);
+            let (addr, server): (SocketAddr, _) =
+                warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+            tokio::spawn(server);
+            format!("http://{}/api", addr)
+        }
+
+        pub fn calls(&self) -> Vec<ApiCall> {
+            self.calls.lock().clone()
+        }
+
+        pub fn reads(&self) -> Vec<ApiCall> {
+            self.reads.lock().clone()
+        }
+
+        fn read(&self, call: ApiCall) -> serde_json::Value {
+            let response = match call.method.as_str() {
+                m if self.failing.lock().contains(&m) => serde_json::json!({
+                    "ok": false,
+                    "error": "missing_scope",
+                }),
+                "chat.getPermalink" => serde_json::json!({
+                    "ok": true,
+                    "channel": call.payload["channel"],
+                    "permalink": format!(
+                        "https://example.slack.com/archives/{}/p{}",
+                        call.payload["channel"].as_str().unwrap_or_default(),
+                        call.payload["message_ts"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .replace('.', ""),
+                    ),
+                }),
+                _ => serde_json::json!({
+                    "ok": false,
+                    "error": "unknown_method",
+                }),
+            };
+            self.reads.lock().push(call);
+            response
+        }
+
+        fn call(&self, call: ApiCall) -> serde_json::Value {
+            let mut calls = self.calls.lock();
+            let response = match call.method.as_str() {
+                _ if call.payload["channel"] == "missing" => serde_json::json!({
+                    "ok": false,
+                    "error": "channel_not_found",
+                }),
+                m if self.failing.lock().contains(&m) => serde_json::json!({
+                    "ok": false,
+                    "error": "missing_scope",
+                }),
+                "chat.postMessage" => serde_json::json!({
+                    "ok": true,
+                    "channel": "C1234",
+                    "ts": format!("{}.000", calls.len()),
+                }),
+                "chat.update" => serde_json::json!({
+                    "ok": true,
+                    "channel": call.payload["channel"],
+                    "ts": call.payload["ts"],
+                }),
+                "chat.scheduleMessage" => serde_json::json!({
+                    "ok": true,
+                    "channel": "C1234",
+                    "scheduled_message_id": format!("Q{}", calls.len()),
+                    "post_at": call.payload["post_at"],
+                }),
+                "reactions.add"
+                | "reactions.remove"
+                | "pins.add"
+                | "pins.remove"
+                | "conversations.setTopic"
+                | "chat.delete"
+                | "chat.deleteScheduledMessage" => {
+                    serde_json::json!({
+                        "ok": true,
+                    })
+                }
+                _ => serde_json::json!({
+                    "ok": false,
+                    "error": "unknown_method",
+                }),
+            };
+            calls.push(call);
+            response
+        }
+    }
+}
+
+fn notification(message: &str) -> Notification {
+    let mut subtemplates: HashMap<String, String> = HashMap::new();
+    subtemplates.insert(
+        "primary".into(),
+        r#"{"text": "Primary: {{message}}"}"#.into(),
+    );
+    subtemplates.insert(
+        "secondary".into(),
+        r#"{"text": "Secondary: {{message}}"}"#.into(),
+    );
+    Notification {
+        template: Arc::new(subtemplates),
+        context: serde_json::json!({ "message": message }),
+    }
+}
+
+fn setup() -> (mocks::SlackApi, Slack) {
+    let api = mocks::SlackApi::default();
+    let service = Slack::from_resolved_config(serde_json::json!({
+        "token": "xoxb-test",
+        "icon_emoji": ":rocket:",
+        "api_url": api.start(),
+    }))
+    .expect("Invalid config");
+    (api, service)
+}
+
+#[tokio::test]
+async fn test_notify_post() {
+    let (api, service) = setup();
+
+    service
+        .notify(
+            serde_json::json!({"channel": "sandbox"}),
+            notification("Hello"),
+        )
+        .await
+        .expect("Notify failed");
+
+    let calls = api.calls();
+    assert_eq!(calls.len(), 2);
+    assert_eq!(calls[0].method, "chat.postMessage");
+    assert_eq!(calls[0].authorization.as_deref(), Some("Bearer xoxb-test"));
+    assert_eq!(calls[0].payload["channel"], "sandbox");
+    assert_eq!(calls[0].payload["ts"], serde_json::Value::Null);
+    assert_eq!(calls[0].payload["icon_emoji"], ":rocket:");
+    assert_eq!(calls[0].payload["text"], "Primary: Hello");
+}
+
+#[tokio::test]
+async fn test_notify_thread() {
+    let (api, service) = setup();
+
+    service
+        .notify(
+            serde_json::json!({"channel": "sandbox"}),
+            notification("Hello"),
+        )
+        .await
+        .expect("Notify failed");
+
+    let calls = api.calls();
+    assert_eq!(calls[1].method, "chat.postMessage");
+    assert_eq!(calls[1].payload["channel"], "C1234");
+    assert_eq!(calls[1].payload["thread_ts"], "0.000");
+    assert_eq!(calls[1].payload["text"], "Secondary: Hello");
+}
+
+#[tokio::test]
+async fn test_notify_update() {
+    let (api, service) = setup();
+
+    for message in ["Started", "Succeeded"] {
+        service
+            .notify(
+                serde_json::json!({"channel": "sandbox"}),
+                notification(message),
+            )
+            .await
+            .expect("Notify failed");
+    }
+
+    // Updates are issued concurrently, so the order of the calls is not guaranteed
+    let mut calls = api.calls();
+    calls[2..].sort_by(|a, b| a.method.cmp(&b.method));
+    assert_eq!(calls.len(), 4);
+    assert_eq!(calls[2].method, "chat.postMessage");
+    assert_eq!(calls[2].payload["thread_ts"], "0.000");
+    assert_eq!(calls[2].payload["text"], "Secondary: Succeeded");
+    assert_eq!(calls[3].method, "chat.update");
+    assert_eq!(calls[3].payload["channel"], "C1234");
+    assert_eq!(calls[3].payload["ts"], "0.000");
+    assert_eq!(calls[3].payload["text"], "Primary: Succeeded");
+}
+
+#[tokio::test]
+async fn test_notify_outputs() {
+    let (api, service) = setup();
+
+    for message in ["Started", "Succeeded"] {
+        let delivery = service
+            .notify(
+                serde_json::json!({"channel": "sandbox"}),
+                notification(message),
+            )
+            .await
+            .expect("Notify failed");
+        assert_eq!(delivery.outputs["channel"], "C1234");
+        assert_eq!(delivery.outputs["ts"], "0.000");
+        assert_eq!(
+            delivery.outputs["permalink"],
+            "https://example.slack.com/archives/C1234/p0000"
+        );
+    }
+
+    // The permalink of a message does not change, so it is retrieved only once
+    let reads = api.reads();
+    assert_eq!(reads.len(), 1);
+    assert_eq!(reads[0].method, "chat.getPermalink");
+    assert_eq!(reads[0].authorization.as_deref(), Some("Bearer xoxb-test"));
+    assert_eq!(reads[0].payload["message_ts"], "0.000");
+}
+
+#[tokio::test]
+async fn test_notify_permalink_failure() {
+    let (api, service) = setup();
+    api.failing.lock().push("chat.getPermalink");
+
+    for message in ["Started", "Succeeded"] {
+        let delivery = service
+            .notify(
+                serde_json::json!({"channel": "sandbox"}),
+                notification(message),
+            )
+            .await
+            .expect("Notify failed");
+        assert_eq!(delivery.outputs["ts"], "0.000");
+        assert_eq!(delivery.outputs["permalink"], "");
+    }
+
+    // The message is updated rather than posted again, and the link is retried every time
+    let calls = api.calls();
+    assert_eq!(calls.len(), 4);
+    assert!(calls.iter().any(|c| c.method == "chat.update"));
+    assert_eq!(api.reads().len(), 2);
+}
+
+#[tokio::test]
+async fn test_notify_pin_failure() {
+    let (api, service) = setup();
+    api.failing.lock().push("pins.add");
+
+    for (message, reaction) in [
+        ("Started", ":hourglass:"),
+        ("Deployed", ":white_check_mark:"),
+    ] {
+        let result = service
+            .notify(
+                serde_json::json!({
+                    "channel": "sandbox",
+                    "pin": true,
+                    "reaction": reaction,
+                    "remove_previous_reaction": true,
+                }),
+                notification(message),
+            )
+            .await;
+        assert!(result.is_err());
+    }
+
+    // The message that was posted before the pinning failed is updated, not posted again
+    let calls = api.calls();
+    let posts = calls
+        .iter()
+        .filter(|c| c.method == "chat.postMessage" && c.payload["thread_ts"].is_null())
+        .count();
+    assert_eq!(posts, 1);
+    assert!(calls
+        .iter()
+        .any(|c| c.method == "chat.update" && c.payload["ts"] == "0.000"));
+
+    // The reaction and the replies that were sent before the pinning failed are kept track of
+    let removed: Vec<_> = calls
+        .iter()
+        .filter(|c| c.method == "reactions.remove")
+        .map(|c| c.payload["name"].clone())
+        .collect();
+    assert_eq!(removed, vec!["hourglass"]);
+    api.failing.lock().clear();
+    service
+        .notify(
+            serde_json::json!({"channel": "sandbox", "cleanup": "thread"}),
+            notification("Succeeded"),
+        )
+        .await
+        .expect("Notify failed");
+    let deleted = api
+        .calls()
+        .iter()
+        .filter(|c| c.method == "chat.delete")
+        .count();
+    assert_eq!(deleted, 2);
+}
+
+#[tokio::test]
+async fn test_notify_restored_state() {
+    let api = mocks::SlackApi::default();
+    let config = serde_json::json!({
+        "token": "xoxb-test",
+        "api_url": api.start(),
+    });
+    let service = Slack::from_resolved_config(config.clone()).expect("Invalid config");
+    service
+        .notify(
+            serde_json::json!({"channel": "sandbox"}),
+            notification("Started"),
+        )
+        .await
+        .expect("Notify failed");
+
+    // A new instance restored from the exported state keeps updating the same message
+    let restored = Slack::from_resolved_config(config).expect("Invalid config");
+    restored.restore(service.state()).expect("Restore failed");
+    restored
+        .notify(
+            serde_json::json!({"channel": "sandbox"}),
+            notification("Succeeded"),
+        )
+        .await
+        .expect("Notify failed");
+
+    let mut calls = api.calls();
+    calls[2..].sort_by(|a, b| a.method.cmp(&b.method));
+    assert_eq!(calls.len(), 4);
+    assert_eq!(calls[2].method, "chat.postMessage");
+    assert_eq!(calls[2].payload["thread_ts"], "0.000");
+    assert_eq!(calls[3].method, "chat.update");
+    assert_eq!(calls[3].payload["ts"], "0.000");
+}
+
+#[tokio::test]
+async fn test_notify_without_secondary() {
+    let (api, service) = setup();
+
+    let mut subtemplates: HashMap<String, String> = HashMap::new();
+    subtemplates.insert("primary".into(), r#"{"text": "Hello"}"#.into());
+    service
+        .notify(
+            serde_json::json!({"channel": "sandbox"}),
+            Notification {
+                template: Arc::new(subtemplates),
+                context: serde_json::json!({}),
+            },
+        )
+        .await
+        .expect("Notify failed");
+
+    let calls = api.calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].method, "chat.postMessage");
+    assert_eq!(calls[0].payload["text"], "Hello");
+}
+
+#[tokio::test]
+async fn test_notify_message_options() {
+    let (api, service) = setup();
+
+    service
+        .notify(
+            serde_json::json!({
+                "channel": "sandbox",
+                "reply_broadcast": true,
+                "unfurl_links": false,
+                "unfurl_media": false,
+                "username": "Deployer",
+                "icon_url": "https://example.com/icon.png",
+            }),
+            notification("Hello"),
+        )
+        .await
+        .expect("Notify failed");
+
+    let calls = api.calls();
+    for call in &calls {
+        assert_eq!(call.payload["unfurl_links"], false);
+        assert_eq!(call.payload["unfurl_media"], false);
+        assert_eq!(call.payload["username"], "Deployer");
+        assert_eq!(call.payload["icon_url"], "https://example.com/icon.png");
+        assert_eq!(call.payload["icon_emoji"], serde_json::Value::Null);
+    }
+    assert_eq!(calls[0].payload.get("reply_broadcast"), None);
+    assert_eq!(calls[1].payload["reply_broadcast"], true);
+}
+
+#[tokio::test]
+async fn test_notify_thread_key() {
+    let (api, service) = setup();
+
+    for thread_key in ["frontend", "backend", "frontend"] {
+        service
+            .notify(
+                serde_json::json!({"channel": "sandbox", "thread_key": thread_key}),
+                notification(thread_key),
+            )
+            .await
+            .expect("Notify failed");
+    }
+
+    let mut calls = api.calls();
+    calls[4..].sort_by(|a, b| a.method.cmp(&b.method));
+    assert_eq!(calls.len(), 6);
+    assert_eq!(calls[0].method, "chat.postMessage");
+    assert_eq!(calls[0].payload["text"], "Primary: frontend");
+    assert_eq!(calls[2].method, "chat.postMessage");
+    assert_eq!(calls[2].payload["text"], "Primary: backend");
+    assert_eq!(calls[3].payload["thread_ts"], "2.000");
+    assert_eq!(calls[4].payload["thread_ts"], "0.000");
+    assert_eq!(calls[5].method, "chat.update");
+    assert_eq!(calls[5].payload["ts"], "0.000");
+}
+
+#[tokio::test]
+async fn test_notify_reaction() {
+    let (api, service) = setup();
+
+    for reaction in [":hourglass:", ":white_check_mark:"] {
+        service
+            .notify(
+                serde_json::json!({
+                    "channel": "sandbox",
+                    "reaction": reaction,
+                    "remove_previous_reaction": true,
+                }),
+                notification("Hello"),
+            )
+            .await
+            .expect("Notify failed");
+    }
+
+    let calls: Vec<_> = api
+        .calls()
+        .into_iter()
+        .filter(|c| c.method.starts_with("reactions."))
+        .collect();
+    assert_eq!(calls.len(), 3);
+    assert_eq!(calls[0].method, "reactions.add");
+    assert_eq!(calls[0].payload["name"], "hourglass");
+    assert_eq!(calls[0].payload["timestamp"], "0.000");
+    assert_eq!(calls[1].method, "reactions.remove");
+    assert_eq!(calls[1].payload["name"], "hourglass");
+    assert_eq!(calls[2].method, "reactions.add");
+    assert_eq!(calls[2].payload["name"], "white_check_mark");
+}
+
+#[tokio::test]
+async fn test_notify_schedule() {
+    let (api, service) = setup();
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let delivery = service
+        .notify(
+            serde_json::json!({"channel": "sandbox", "delay": 600}),
+            notification("Canary still running"),
+        )
+        .await
+        .expect("Notify failed");
+
+    assert_eq!(
+        delivery.message.as_deref(),
+        Some("Notification scheduled: Q0")
+    );
+    let calls = api.calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].method, "chat.scheduleMessage");
+    assert_eq!(calls[0].payload["channel"], "sandbox");
+    assert_eq!(calls[0].payload["text"], "Primary: Canary still running");
+    let post_at = calls[0].payload["post_at"].as_u64().unwrap();
+    assert!(post_at >= now + 600 && post_at <= now + 610);
+}
+
+#[tokio::test]
+async fn test_notify_reschedule() {
+    let (api, service) = setup();
+
+    for post_at in [2000000000, 2000000600] {
+        service
+            .notify(
+                serde_json::json!({"channel": "sandbox", "post_at": post_at}),
+                notification("Reminder"),
+            )
+            .await
+            .expect("Notify failed");
+    }
+
+    let calls = api.calls();
+    assert_eq!(calls.len(), 3);
+    assert_eq!(calls[1].method, "chat.deleteScheduledMessage");
+    assert_eq!(calls[1].payload["channel"], "C1234");
+    assert_eq!(calls[1].payload["scheduled_message_id"], "Q0");
+    assert_eq!(calls[2].method, "chat.scheduleMessage");
+    assert_eq!(calls[2].payload["post_at"], 2000000600);
+}
+
+#[tokio::test]
+async fn test_notify_cancel_scheduled() {
+    let (api, service) = setup();
+
+    service
+        .notify(
+            serde_json::json!({"channel": "sandbox", "thread_key": "canary", "delay": 600}),
+            notification("Reminder"),
+        )
+        .await
+        .expect("Notify failed");
+    let mut messages = vec![];
+    for _ in 0..2 {
+        let delivery = service
+            .notify(
+                serde_json::json!({
+                    "channel": "sandbox",
+                    "thread_key": "canary",
+                    "cancel_scheduled": true,
+                }),
+                notification("Reminder"),
+            )
+            .await
+            .expect("Notify failed");
+        messages.push(delivery.message.unwrap());
+    }
+
+    assert_eq!(
+        messages,
+        vec![
+            "Scheduled notification cancelled",
+            "No scheduled notification to cancel"
+        ]
+    );
+    let calls = api.calls();
+    assert_eq!(calls.len(), 2);
+    assert_eq!(calls[1].method, "chat.deleteScheduledMessage");
+    assert_eq!(calls[1].payload["scheduled_message_id"], "Q0");
+}
+
+#[tokio::test]
+async fn test_notify_cleanup_thread() {
+    let (api, service) = setup();
+
+    for message in ["Started", "Deployed"] {
+        service
+            .notify(
+                serde_json::json!({"channel": "sandbox"}),
+                notification(message),
+            )
+            .await
+            .expect("Notify failed");
+    }
+    service
+        .notify(
+            serde_json::json!({"channel": "sandbox", "cleanup": "thread"}),
+            notification("Succeeded"),
+        )
+        .await
+        .expect("Notify failed");
+
+    let mut calls = api.calls();
+    calls[4..].sort_by(|a, b| a.method.cmp(&b.method));
+    let thread: Vec<_> = calls[..4]
+        .iter()
+        .filter(|c| c.payload.get("thread_ts").is_some())
+        .map(|c| c.payload["text"].clone())
+        .collect();
+    assert_eq!(thread, vec!["Secondary: Started", "Secondary: Deployed"]);
+    assert_eq!(calls.len(), 7);
+    assert_eq!(calls[4].method, "chat.delete");
+    assert_eq!(calls[4].payload["ts"], "1.000");
+    assert_eq!(calls[5].method, "chat.delete");
+    assert_eq!(calls[6].method, "chat.update");
+    assert_eq!(calls[6].payload["text"], "Primary: Succeeded");
+
+    // The thread starts from scratch after the cleanup
+    service
+        .notify(
+            serde_json::json!({"channel": "sandbox", "cleanup": "thread"}),
+            notification("Succeeded"),
+        )
+        .await
+        .expect("Notify failed");
+    let calls = api.calls();
+    assert_eq!(calls.len(), 8);
+    assert_eq!(calls[7].method, "chat.update");
+}
+
+#[tokio::test]
+async fn test_notify_cleanup_all() {
+    let (api, service) = setup();
+
+    service
+        .notify(
+            serde_json::json!({"channel": "sandbox"}),
+            notification("Started"),
+        )
+        .await
+        .expect("Notify failed");
+    let delivery = service
+        .notify(
+            serde_json::json!({"channel": "sandbox", "cleanup": "all"}),
+            notification("Succeeded"),
+        )
+        .await
+        .expect("Notify failed");
+
+    assert_eq!(delivery.message.as_deref(), Some("Deleted 2 message(s)"));
+    let calls = api.calls();
+    assert_eq!(calls.len(), 4);
+    assert_eq!(calls[2].method, "chat.delete");
+    assert_eq!(calls[2].payload["channel"], "C1234");
+    assert_eq!(calls[2].payload["ts"], "1.000");
+    assert_eq!(calls[3].method, "chat.delete");
+    assert_eq!(calls[3].payload["ts"], "0.000");
+
+    // The messages are no longer tracked, so the next notification starts a new message
+    service
+        .notify(
+            serde_json::json!({"channel": "sandbox"}),
+            notification("Started"),
+        )
+        .await
+        .expect("Notify failed");
+    let calls = api.calls();
+    assert_eq!(calls[4].method, "chat.postMessage");
+    assert_eq!(calls[4].payload["channel"], "sandbox");
+}
+
+#[tokio::test]
+async fn test_notify_pin() {
+    let (api, service) = setup();
+
+    for pin in [true, false] {
+        service
+            .notify(
+                serde_json::json!({"channel": "sandbox", "pin": pin}),
+                notification("Hello"),
+            )
+            .await
+            .expect("Notify failed");
+    }
+
+    let calls: Vec<_> = api
+        .calls()
+        .into_iter()
+        .filter(|c| c.method.starts_with("pins."))
+        .collect();
+    assert_eq!(calls.len(), 2);
+    assert_eq!(calls[0].method, "pins.add");
+    assert_eq!(calls[0].payload["channel"], "C1234");
+    assert_eq!(calls[0].payload["timestamp"], "0.000");
+    assert_eq!(calls[1].method, "pins.remove");
+    assert_eq!(calls[1].payload["timestamp"], "0.000");
+}
+
+#[tokio::test]
+async fn test_notify_topic() {
+    let (api, service) = setup();
+
+    for status in ["In progress", "In progress", "Done"] {
+        let mut subtemplates: HashMap<String, String> = HashMap::new();
+        subtemplates.insert("primary".into(), r#"{"text": "Hello"}"#.into());
+        subtemplates.insert("topic".into(), "Release: {{status}}\n".into());
+        service
+            .notify(
+                serde_json::json!({"channel": "sandbox", "set_topic": true}),
+                Notification {
+                    template: Arc::new(subtemplates),
+                    context: serde_json::json!({ "status": status }),
+                },
+            )
+            .await
+            .expect("Notify failed");
+    }
+
+    let topics: Vec<_> = api
+        .calls()
+        .into_iter()
+        .filter(|c| c.method == "conversations.setTopic")
+        .map(|c| c.payload)
+        .collect();
+    assert_eq!(
+        topics,
+        vec![
+            serde_json::json!({"channel": "C1234", "topic": "Release: In progress"}),
+            serde_json::json!({"channel": "C1234", "topic": "Release: Done"}),
+        ]
+    );
+}
+
+fn blocks_notification(primary: serde_json::Value) -> Notification {
+    let mut subtemplates: HashMap<String, String> = HashMap::new();
+    subtemplates.insert("primary".into(), primary.to_string());
+    Notification {
+        template: Arc::new(subtemplates),
+        context: serde_json::json!({}),
+    }
+}
+
+#[tokio::test]
+async fn test_notify_invalid_blocks() {
+    let (api, service) = setup();
+
+    let cases = [
+        (
+            serde_json::json!({"blocks": {"type": "divider"}}),
+            "blocks has to be an array",
+        ),
+        (
+            serde_json::json!({"blocks": vec![serde_json::json!({"type": "divider"}); 51]}),
+            "too many blocks (51), at most 50 are allowed",
+        ),
+        (
+            serde_json::json!({"blocks": [
+                {"type": "divider"},
+                {"type": "section", "text": {"type": "mrkdwn", "text": "a".repeat(3001)}},
+            ]}),
+            "block 1 (section): text.text is too long (3001 characters), at most 3000 are allowed",
+        ),
+        (
+            serde_json::json!({"blocks": [
+                {"type": "section", "fields": [{"type": "mrkdwn", "text": ""}]},
+            ]}),
+            "block 0 (section): fields[0].text must not be empty",
+        ),
+        (
+            serde_json::json!({"blocks": [
+                {"type": "header", "text": {"type": "mrkdwn", "text": "Release"}},
+            ]}),
+            "block 0 (header): text.type \"mrkdwn\" is not allowed",
+        ),
+        (
+            serde_json::json!({"blocks": [{"type": "actions", "elements": []}]}),
+            "block 0 (actions): elements needs at least 1 item(s)",
+        ),
+        (
+            serde_json::json!({"blocks": [{"type": "image", "image_url": "https://example.com"}]}),
+            "block 0 (image): alt_text has to be a string",
+        ),
+        (
+            serde_json::json!({"blocks": [{"text": "Hello"}]}),
+            "block 0: type is missing",
+        ),
+    ];
+    for (primary, error) in cases {
+        let result = service
+            .notify(
+                serde_json::json!({"channel": "sandbox"}),
+                blocks_notification(primary),
+            )
+            .await;
+        match result {
+            Err(CallError::RenderError(e)) => assert_eq!(
+                e,
+                format!("Invalid blocks in the \"primary\" sub-template: {}", error)
+            ),
+            _ => panic!("Unexpected result"),
+        }
+    }
+    assert_eq!(api.calls().len(), 0);
+}
+
+#[tokio::test]
+async fn test_notify_valid_blocks() {
+    let (api, service) = setup();
+
+    let blocks = serde_json::json!([
+        {"type": "header", "text": {"type": "plain_text", "text": "Release"}},
+        {"type": "section", "text": {"type": "mrkdwn", "text": "Deployed"}},
+        {"type": "divider"},
+        {"type": "context", "elements": [
+            {"type": "image", "image_url": "https://example.com", "alt_text": "logo"},
+            {"type": "mrkdwn", "text": "<https://example.com|View logs>"},
+        ]},
+        {"type": "markdown", "text": "**Deployed** to _production_"},
+    ]);
+    service
+        .notify(
+            serde_json::json!({"channel": "sandbox"}),
+            blocks_notification(serde_json::json!({"blocks": blocks})),
+        )
+        .await
+        .expect("Notify failed");
+
+    assert_eq!(api.calls()[0].payload["blocks"], blocks);
+}
+
+#[tokio::test]
+async fn test_notify_invalid_blocks_fallback() {
+    let (api, service) = setup();
+
+    let primary = serde_json::json!({
+        "text": "Deployed",
+        "blocks": [{"type": "section"}],
+    });
+    service
+        .notify(
+            serde_json::json!({"channel": "sandbox", "fallback_to_text": true}),
+            blocks_notification(primary),
+        )
+        .await
+        .expect("Notify failed");
+    let result = service
+        .notify(
+            serde_json::json!({"channel": "sandbox", "fallback_to_text": true}),
+            blocks_notification(serde_json::json!({"blocks": [{"type": "section"}]})),
+        )
+        .await;
+
+    let calls = api.calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].payload["text"], "Deployed");
+    assert_eq!(calls[0].payload["blocks"], serde_json::Value::Null);
+    assert!(matches!(result, Err(CallError::RenderError(_))));
+}
+
+#[tokio::test]
+async fn test_notify_error_response() {
+    let (api, service) = setup();
+
+    let result = service
+        .notify(
+            serde_json::json!({"channel": "missing"}),
+            notification("Hello"),
+        )
+        .await;
+
+    match result {
+        Err(CallError::Fail(e)) => assert_eq!(e, "channel_not_found"),
+        _ => panic!("Unexpected result"),
+    }
+    assert_eq!(api.calls().len(), 1);
+}
+
+#[tokio::test]
+async fn test_notify_missing_subtemplate() {
+    let (api, service) = setup();
+
+    let result = service
+        .notify(
+            serde_json::json!({"channel": "sandbox"}),
+            Notification {
+                template: Arc::new(HashMap::new()),
+                context: serde_json::json!({}),
+            },
+        )
+        .await;
+
+    assert!(matches!(result, Err(CallError::RenderError(_))));
+    assert_eq!(api.calls().len(), 0);
+}